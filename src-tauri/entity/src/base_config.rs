@@ -21,6 +21,13 @@ pub struct Model {
     pub allow_lan: bool,
     pub mode: String,
     pub log_level: String,
+    /// Serve the enabled nodes as a subscription on `local_ip`
+    pub share_server_enabled: bool,
+    pub share_server_port: u16,
+    /// Required `token` query parameter of the share server
+    pub share_server_token: String,
+    /// Drop imported nodes that duplicate an enabled node of another source
+    pub skip_duplicate_nodes: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -52,6 +59,10 @@ impl Model {
                     mode: "Rules".to_string(),
                     update_interval: 3,
                     log_level: "debug".to_string(),
+                    share_server_enabled: false,
+                    share_server_port: 10088,
+                    share_server_token: "".to_string(),
+                    skip_duplicate_nodes: false,
                 };
                 let _ = default_config.insert_one(db).await?;
             }
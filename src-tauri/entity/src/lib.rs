@@ -7,6 +7,7 @@ pub mod base_config;
 pub mod hysteria;
 pub mod rules;
 pub mod subscribe;
+pub mod subscription_snapshot;
 pub mod types;
 pub mod utils;
 pub mod xray;
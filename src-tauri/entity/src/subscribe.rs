@@ -1,5 +1,7 @@
-use sea_orm::{entity::prelude::*, ActiveValue::NotSet};
+use sea_orm::sea_query::Query;
+use sea_orm::{entity::prelude::*, ActiveValue::NotSet, Condition, FromJsonQueryResult};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, DeriveEntityModel)]
 #[sea_orm(table_name = "subscribe")]
@@ -8,16 +10,120 @@ pub struct Model {
     pub id: i32,
     pub url: String,
     pub name: String,
-    pub is_active: bool,
+    /// Disabled subscriptions keep their nodes but hide them and are not refreshed.
+    pub enabled: bool,
     pub created_at: ChronoDateTimeUtc,
     pub updated_at: ChronoDateTimeUtc,
     pub last_sync_at: Option<ChronoDateTimeUtc>,
+    /// Traffic reported by the provider's `subscription-userinfo` header, in bytes.
+    pub upload: Option<i64>,
+    pub download: Option<i64>,
+    pub total: Option<i64>,
+    pub expire_at: Option<ChronoDateTimeUtc>,
+    /// Refresh interval in hours suggested by the `profile-update-interval` header.
+    pub update_interval: Option<i32>,
+    /// User agent sent when fetching, `None` uses the built-in default.
+    pub user_agent: Option<String>,
+    /// Extra request headers sent when fetching.
+    #[sea_orm(column_type = "Text", nullable)]
+    pub headers: Option<RequestHeaders>,
+    pub timeout_secs: Option<i32>,
+    pub fetch_mode: FetchMode,
+    /// Rules applied to parsed nodes before they are saved.
+    #[sea_orm(column_type = "Text", nullable)]
+    pub filters: Option<NodeFilters>,
+    pub kind: SubscriptionKind,
+    /// Body of a pasted-text subscription.
+    #[sea_orm(column_type = "Text", nullable)]
+    pub content: Option<String>,
+}
+
+/// Per-subscription node filters. Regexes match against the node name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+#[serde(default)]
+pub struct NodeFilters {
+    /// Keep only nodes whose name matches.
+    pub include: Option<String>,
+    /// Drop nodes whose name matches.
+    pub exclude: Option<String>,
+    /// Keep only these protocols (`vless`, `vmess`, `trojan`, `shadowsocks`, `tuic`, `hysteria2`);
+    /// empty keeps all.
+    pub protocols: Vec<String>,
+    /// Applied in order to the names of the remaining nodes.
+    pub renames: Vec<RenameRule>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenameRule {
+    pub pattern: String,
+    /// Replacement template, may reference capture groups like `$1` or `${name}`.
+    pub replacement: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct RequestHeaders(pub HashMap<String, String>);
+
+/// How a subscription is downloaded.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum FetchMode {
+    #[default]
+    #[serde(rename = "direct")]
+    #[sea_orm(string_value = "direct")]
+    Direct,
+    /// Through the local HTTP proxy
+    #[serde(rename = "proxy")]
+    #[sea_orm(string_value = "proxy")]
+    Proxy,
+    /// Through the local HTTP proxy, falling back to a direct connection
+    #[serde(rename = "auto")]
+    #[sea_orm(string_value = "auto")]
+    Auto,
+}
+
+/// Where the body of a subscription comes from.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum SubscriptionKind {
+    /// Downloaded from an `http://` or `https://` URL
+    #[default]
+    #[serde(rename = "remote")]
+    #[sea_orm(string_value = "remote")]
+    Remote,
+    /// Read from a `file://` URL on every refresh
+    #[serde(rename = "file")]
+    #[sea_orm(string_value = "file")]
+    File,
+    /// Pasted text stored in `content`
+    #[serde(rename = "text")]
+    #[sea_orm(string_value = "text")]
+    Text,
+}
+
+impl SubscriptionKind {
+    /// Kind of a subscription URL, `None` if the scheme is not supported.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let url = url.trim();
+        if url.starts_with("http://") || url.starts_with("https://") {
+            Some(Self::Remote)
+        } else if url.starts_with("file://") {
+            Some(Self::File)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::xray::Entity")]
     Xray,
+    #[sea_orm(has_many = "super::hysteria::Entity")]
+    Hysteria,
 }
 
 impl Related<super::xray::Entity> for Entity {
@@ -26,8 +132,28 @@ impl Related<super::xray::Entity> for Entity {
     }
 }
 
+impl Related<super::hysteria::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Hysteria.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
 impl Model {
     generate_model_functions!();
+
+    /// Condition matching nodes that were added manually or belong to an enabled
+    /// subscription, for a node table's `subscribe_id` column.
+    pub fn enabled_nodes_condition<C: ColumnTrait>(subscribe_id: C) -> Condition {
+        Condition::any().add(subscribe_id.is_null()).add(
+            subscribe_id.in_subquery(
+                Query::select()
+                    .column(Column::Id)
+                    .from(Entity)
+                    .and_where(Column::Enabled.eq(true))
+                    .to_owned(),
+            ),
+        )
+    }
 }
@@ -0,0 +1,113 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{NotSet, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+/// Raw body of a subscription fetch, kept so a subscription can be rolled back.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "subscription_snapshot")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i32,
+    pub subscribe_id: i32,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    #[sea_orm(column_type = "Text")]
+    pub content: String,
+    pub created_at: ChronoDateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::subscribe::Entity",
+        from = "Column::SubscribeId",
+        to = "super::subscribe::Column::Id"
+    )]
+    Subscribe,
+}
+
+impl Related<super::subscribe::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Subscribe.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    generate_model_functions!();
+
+    /// Snapshots of a subscription, newest first.
+    pub async fn fetch_by_subscribe_id<C>(db: &C, subscribe_id: i32) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let results = Entity::find()
+            .filter(Column::SubscribeId.eq(subscribe_id))
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(results)
+    }
+
+    pub async fn latest<C>(db: &C, subscribe_id: i32) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::find()
+            .filter(Column::SubscribeId.eq(subscribe_id))
+            .order_by_desc(Column::Id)
+            .one(db)
+            .await?;
+        Ok(result)
+    }
+
+    /// Delete all but the `keep` newest snapshots of a subscription.
+    pub async fn prune<C>(db: &C, subscribe_id: i32, keep: usize) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let stale_ids: Vec<i32> = Entity::find()
+            .select_only()
+            .column(Column::Id)
+            .filter(Column::SubscribeId.eq(subscribe_id))
+            .order_by_desc(Column::Id)
+            .into_tuple::<i32>()
+            .all(db)
+            .await?
+            .into_iter()
+            .skip(keep)
+            .collect();
+        if !stale_ids.is_empty() {
+            Self::delete_by_ids(db, stale_ids).await?;
+        }
+        Ok(())
+    }
+
+    /// Forget the cache validators of a subscription's snapshots, so the next
+    /// refresh downloads the full body, e.g. after the URL changed.
+    pub async fn clear_validators<C>(db: &C, subscribe_id: i32) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let _ = Entity::update_many()
+            .col_expr(Column::Etag, Expr::value(Option::<String>::None))
+            .col_expr(Column::LastModified, Expr::value(Option::<String>::None))
+            .filter(Column::SubscribeId.eq(subscribe_id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_subscribe_id<C>(db: &C, subscribe_id: i32) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let _ = Entity::delete_many()
+            .filter(Column::SubscribeId.eq(subscribe_id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -5,11 +5,12 @@ use serde::{Deserialize, Serialize};
 
 use uuid::Uuid;
 
-const XRAY_SCHEMAS: [&str; 3] = ["vmess", "vless", "trojan"];
-const HYSTERIA_SCHEMAS: [&str; 1] = ["hy2"];
+const XRAY_SCHEMAS: [&str; 5] = ["vmess", "vless", "trojan", "ss", "tuic"];
+pub(crate) const HYSTERIA_SCHEMAS: [&str; 2] = ["hy2", "hysteria2"];
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ShareJsonStruct {
+    #[serde(default)]
     pub ps: String,
     pub add: String,
     #[serde(deserialize_with = "port_as_u16")]
@@ -1,6 +1,8 @@
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use rand::prelude::*;
 use std::collections::HashSet;
 use std::net::TcpListener;
+use url::Url;
 
 const START_PORT: u16 = 20000;
 const END_PORT: u16 = 30000;
@@ -26,3 +28,29 @@ pub fn get_random_port(used_ports: &HashSet<u16>) -> Option<u16> {
 
     None
 }
+
+pub fn decode_url_component(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+/// The node remark carried in the `#fragment` of a share link, if any.
+pub fn fragment_name(url: &Url) -> Option<String> {
+    let name = decode_url_component(url.fragment()?);
+    let name = name.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Characters escaped in share link components: everything but RFC 3986 unreserved.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+pub fn encode_url_component(value: &str) -> String {
+    utf8_percent_encode(value, COMPONENT).to_string()
+}
@@ -7,6 +7,19 @@ mod m20240205_054639_add_rules;
 mod m20241126_062352_add_lang_col;
 mod m20260125_202910_add_log_level;
 mod m20260130_120000_add_subscription_fields;
+mod m20261017_090000_add_hysteria_subscribe_id;
+mod m20261017_100000_add_xray_shadowsocks;
+mod m20261017_110000_add_subscription_userinfo;
+mod m20261017_120000_add_subscription_fetch_options;
+mod m20261017_130000_add_subscription_filters;
+mod m20261017_140000_create_subscription_snapshot;
+mod m20261017_150000_add_subscription_source;
+mod m20261017_160000_add_subscription_enabled;
+mod m20261017_170000_add_share_server;
+mod m20261017_180000_add_skip_duplicate_nodes;
+mod m20261017_190000_add_xray_flow;
+mod m20261017_200000_add_hysteria_port_hopping;
+mod m20261017_210000_add_xray_tuic;
 
 pub struct Migrator;
 
@@ -21,6 +34,37 @@ impl MigratorTrait for Migrator {
             Box::new(m20241126_062352_add_lang_col::Migration),
             Box::new(m20260125_202910_add_log_level::Migration),
             Box::new(m20260130_120000_add_subscription_fields::Migration),
+            Box::new(m20261017_090000_add_hysteria_subscribe_id::Migration),
+            Box::new(m20261017_100000_add_xray_shadowsocks::Migration),
+            Box::new(m20261017_110000_add_subscription_userinfo::Migration),
+            Box::new(m20261017_120000_add_subscription_fetch_options::Migration),
+            Box::new(m20261017_130000_add_subscription_filters::Migration),
+            Box::new(m20261017_140000_create_subscription_snapshot::Migration),
+            Box::new(m20261017_150000_add_subscription_source::Migration),
+            Box::new(m20261017_160000_add_subscription_enabled::Migration),
+            Box::new(m20261017_170000_add_share_server::Migration),
+            Box::new(m20261017_180000_add_skip_duplicate_nodes::Migration),
+            Box::new(m20261017_190000_add_xray_flow::Migration),
+            Box::new(m20261017_200000_add_hysteria_port_hopping::Migration),
+            Box::new(m20261017_210000_add_xray_tuic::Migration),
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm_migration::sea_orm::Database;
+
+    // The app tests build their tables from the entities, so only this catches a
+    // migration that fails on a fresh database
+    #[async_std::test]
+    async fn test_migrations_apply_to_empty_database() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        let manager = SchemaManager::new(&db);
+        assert!(manager.has_column("subscribe", "update_interval").await.unwrap());
+        assert!(manager.has_table("subscription_snapshot").await.unwrap());
+    }
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            ColumnDef::new(Subscribe::Upload).big_integer().null().to_owned(),
+            ColumnDef::new(Subscribe::Download).big_integer().null().to_owned(),
+            ColumnDef::new(Subscribe::Total).big_integer().null().to_owned(),
+            ColumnDef::new(Subscribe::ExpireAt)
+                .timestamp_with_time_zone()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Subscribe::UpdateInterval).integer().null().to_owned(),
+        ];
+        // SQLite only accepts one column per ALTER TABLE statement
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Subscribe::Table)
+                        .add_column_if_not_exists(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Subscribe::Upload,
+            Subscribe::Download,
+            Subscribe::Total,
+            Subscribe::ExpireAt,
+            Subscribe::UpdateInterval,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Subscribe::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Subscribe {
+    Table,
+    Upload,
+    Download,
+    Total,
+    ExpireAt,
+    UpdateInterval,
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            ColumnDef::new(Subscribe::UserAgent).string().null().to_owned(),
+            ColumnDef::new(Subscribe::Headers).json().null().to_owned(),
+            ColumnDef::new(Subscribe::TimeoutSecs).integer().null().to_owned(),
+            ColumnDef::new(Subscribe::FetchMode)
+                .string()
+                .not_null()
+                .default("direct")
+                .to_owned(),
+        ];
+        // SQLite only accepts one column per ALTER TABLE statement
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Subscribe::Table)
+                        .add_column_if_not_exists(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Subscribe::UserAgent,
+            Subscribe::Headers,
+            Subscribe::TimeoutSecs,
+            Subscribe::FetchMode,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Subscribe::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Subscribe {
+    Table,
+    UserAgent,
+    Headers,
+    TimeoutSecs,
+    FetchMode,
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SubscriptionSnapshot::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SubscriptionSnapshot::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SubscriptionSnapshot::SubscribeId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SubscriptionSnapshot::Etag).string().null())
+                    .col(
+                        ColumnDef::new(SubscriptionSnapshot::LastModified)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(SubscriptionSnapshot::Content)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SubscriptionSnapshot::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_subscription_snapshot_subscribe_id")
+                    .table(SubscriptionSnapshot::Table)
+                    .col(SubscriptionSnapshot::SubscribeId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SubscriptionSnapshot::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SubscriptionSnapshot {
+    Table,
+    Id,
+    SubscribeId,
+    Etag,
+    LastModified,
+    Content,
+    CreatedAt,
+}
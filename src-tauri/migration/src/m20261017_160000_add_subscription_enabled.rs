@@ -0,0 +1,55 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Subscribe::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Subscribe::Enabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Keep the previously active subscription and every subscription that still
+        // has nodes enabled; the others had their nodes deleted when switching away.
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "UPDATE subscribe SET enabled = (is_active \
+                 OR EXISTS (SELECT 1 FROM xray WHERE xray.subscribe_id = subscribe.id) \
+                 OR EXISTS (SELECT 1 FROM hysteria WHERE hysteria.subscribe_id = subscribe.id))"
+                    .to_string(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Subscribe::Table)
+                    .drop_column(Subscribe::Enabled)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Subscribe {
+    Table,
+    Enabled,
+}
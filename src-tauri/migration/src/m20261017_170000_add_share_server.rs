@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            ColumnDef::new(BaseConfig::ShareServerEnabled)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+            ColumnDef::new(BaseConfig::ShareServerPort)
+                .integer()
+                .not_null()
+                .default(10088)
+                .to_owned(),
+            ColumnDef::new(BaseConfig::ShareServerToken)
+                .string()
+                .not_null()
+                .default("")
+                .to_owned(),
+        ];
+        // SQLite only accepts one column per ALTER TABLE statement
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(BaseConfig::Table)
+                        .add_column_if_not_exists(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            BaseConfig::ShareServerEnabled,
+            BaseConfig::ShareServerPort,
+            BaseConfig::ShareServerToken,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(BaseConfig::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum BaseConfig {
+    Table,
+    ShareServerEnabled,
+    ShareServerPort,
+    ShareServerToken,
+}
@@ -1,3 +1,12 @@
 pub mod api_traits;
+pub mod clash;
 pub mod common_apis;
+pub mod duplicates;
 pub mod parse_subscription;
+pub mod qr_codes;
+pub mod share_links;
+pub mod singbox;
+pub mod subscription_apis;
+#[cfg(test)]
+pub mod test_utils;
+pub mod xray_config;
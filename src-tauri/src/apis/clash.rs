@@ -0,0 +1,637 @@
+use std::collections::HashMap;
+
+use entity::hysteria::{self, normalize_bandwidth, Bandwidth, Obfs, PortHopping, Tls};
+use entity::xray::{self, Protocol, ShadowsocksSettings, StreamSettings, TuicSettings};
+use sea_orm::ActiveEnum;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use serde_yaml::Value;
+
+use super::parse_subscription::SubscriptionNodes;
+use super::share_links::{bare_host, strip_nulls, unique_names};
+
+#[derive(Debug, Deserialize)]
+struct ClashConfig {
+    proxies: Vec<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+struct WsOpts {
+    path: Option<String>,
+    headers: HashMap<String, String>,
+    /// HTTPUpgrade instead of a WebSocket handshake
+    v2ray_http_upgrade: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+struct H2Opts {
+    host: Vec<String>,
+    path: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+struct GrpcOpts {
+    grpc_service_name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+struct RealityOpts {
+    public_key: Option<String>,
+    short_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ClashProxy {
+    name: String,
+    #[serde(rename = "type")]
+    r#type: String,
+    server: String,
+    port: u16,
+    uuid: Option<String>,
+    flow: Option<String>,
+    password: Option<String>,
+    cipher: Option<String>,
+    plugin: Option<String>,
+    plugin_opts: Option<HashMap<String, Value>>,
+    network: Option<String>,
+    #[serde(default)]
+    tls: bool,
+    servername: Option<String>,
+    sni: Option<String>,
+    #[serde(default)]
+    skip_cert_verify: bool,
+    ws_opts: Option<WsOpts>,
+    h2_opts: Option<H2Opts>,
+    grpc_opts: Option<GrpcOpts>,
+    reality_opts: Option<RealityOpts>,
+    client_fingerprint: Option<String>,
+    alpn: Option<Vec<String>>,
+    obfs: Option<String>,
+    obfs_password: Option<String>,
+    /// Hysteria2 hopping ports, e.g. `20000-30000`
+    ports: Option<String>,
+    hop_interval: Option<Value>,
+    /// Hysteria2 pinned certificate SHA-256
+    fingerprint: Option<String>,
+    ca: Option<String>,
+    /// TUIC congestion control, `bbr`, `cubic` or `new_reno`
+    congestion_controller: Option<String>,
+    udp_relay_mode: Option<String>,
+    up: Option<Value>,
+    down: Option<Value>,
+}
+
+impl ClashProxy {
+    /// Map the Clash fields onto the share-link parameters understood by
+    /// `StreamSettings::from_query_params`.
+    fn query_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        let http_upgrade = self.ws_opts.as_ref().is_some_and(|x| x.v2ray_http_upgrade);
+        let network = match self.network.as_deref() {
+            Some("ws") if http_upgrade => "httpupgrade",
+            Some(network) => network,
+            None => "tcp",
+        };
+        params.insert("type".to_string(), network.to_string());
+        if let Some(reality) = &self.reality_opts {
+            params.insert("security".into(), "reality".into());
+            if let Some(public_key) = &reality.public_key {
+                params.insert("pbk".into(), public_key.clone());
+            }
+            if let Some(short_id) = &reality.short_id {
+                params.insert("sid".into(), short_id.clone());
+            }
+        } else if self.tls || matches!(self.r#type.as_str(), "trojan" | "tuic") {
+            params.insert("security".into(), "tls".into());
+        }
+        if let Some(server_name) = self.servername.as_ref().or(self.sni.as_ref()) {
+            params.insert("sni".into(), server_name.clone());
+        }
+        if self.skip_cert_verify {
+            params.insert("allowInsecure".into(), "1".into());
+        }
+        if let Some(fingerprint) = &self.client_fingerprint {
+            params.insert("fp".into(), fingerprint.clone());
+        }
+        if let Some(alpn) = &self.alpn {
+            params.insert("alpn".into(), alpn.join(","));
+        }
+        if let Some(ws_opts) = &self.ws_opts {
+            if let Some(path) = &ws_opts.path {
+                params.insert("path".into(), path.clone());
+            }
+            let host = ws_opts
+                .headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("host"))
+                .map(|(_, value)| value.clone());
+            if let Some(host) = host {
+                params.insert("host".into(), host);
+            }
+        }
+        if let Some(h2_opts) = &self.h2_opts {
+            if let Some(path) = &h2_opts.path {
+                params.insert("path".into(), path.clone());
+            }
+            if !h2_opts.host.is_empty() {
+                params.insert("host".into(), h2_opts.host.join(","));
+            }
+        }
+        if let Some(service_name) = self
+            .grpc_opts
+            .as_ref()
+            .and_then(|x| x.grpc_service_name.as_ref())
+        {
+            params.insert("serviceName".into(), service_name.clone());
+        }
+        params
+    }
+
+    fn to_xray(&self, protocol: Protocol) -> anyhow::Result<xray::Model> {
+        let uuid = match protocol {
+            Protocol::Trojan | Protocol::Shadowsocks => self.password.clone(),
+            _ => self.uuid.clone(),
+        }
+        .ok_or_else(|| anyhow::anyhow!("missing credential for {}", self.name))?;
+        let stream_settings = StreamSettings::from_query_params(&self.query_params())?;
+        let mut model = xray::Model::new(
+            self.name.clone(),
+            protocol,
+            uuid,
+            self.server.clone(),
+            self.port,
+            stream_settings,
+        );
+        model.flow = self.flow.clone().filter(|x| !x.is_empty());
+        Ok(model)
+    }
+
+    fn to_shadowsocks(&self) -> anyhow::Result<xray::Model> {
+        let cipher = self
+            .cipher
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("missing cipher for {}", self.name))?;
+        let plugin_opts = self.plugin_opts.as_ref().map(|opts| {
+            opts.iter()
+                .filter_map(|(key, value)| yaml_scalar(&Some(value.clone())).map(|x| format!("{key}={x}")))
+                .collect::<Vec<_>>()
+                .join(";")
+        });
+        let settings = ShadowsocksSettings::new(cipher, self.plugin.clone(), plugin_opts)?;
+        let mut model = self.to_xray(Protocol::Shadowsocks)?;
+        model.shadowsocks = Some(settings);
+        Ok(model)
+    }
+
+    fn to_tuic(&self) -> anyhow::Result<xray::Model> {
+        let password = self
+            .password
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("missing password for {}", self.name))?;
+        let settings = TuicSettings::new(
+            password,
+            self.congestion_controller.clone(),
+            self.udp_relay_mode.clone(),
+        )?;
+        let mut model = self.to_xray(Protocol::Tuic)?;
+        model.tuic = Some(settings);
+        Ok(model)
+    }
+
+    fn to_hysteria(&self) -> anyhow::Result<hysteria::Model> {
+        let auth = self
+            .password
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("missing password for {}", self.name))?;
+        let obfs = match (&self.obfs, &self.obfs_password) {
+            (Some(r#type), Some(password)) if !r#type.is_empty() && r#type != "none" => {
+                Some(Obfs {
+                    r#type: r#type.clone(),
+                    password: password.clone(),
+                })
+            }
+            _ => None,
+        };
+        let port_hopping = match self.ports.as_deref() {
+            Some(ports) => PortHopping::from_ports(ports)?.map(|mut hopping| {
+                hopping.interval = yaml_scalar(&self.hop_interval).and_then(|x| x.parse().ok());
+                hopping
+            }),
+            None => None,
+        };
+        Ok(hysteria::Model {
+            id: Default::default(),
+            name: self.name.clone(),
+            server: format!("{}:{}", self.server, self.port),
+            auth,
+            tls: Tls {
+                sni: self.sni.clone().unwrap_or_else(|| self.server.clone()),
+                insecure: self.skip_cert_verify,
+                pin_sha256: self.fingerprint.clone(),
+                ca: self.ca.clone(),
+            },
+            bandwidth: Bandwidth {
+                up: normalize_bandwidth(yaml_scalar(&self.up).as_ref()),
+                down: normalize_bandwidth(yaml_scalar(&self.down).as_ref()),
+            },
+            obfs,
+            subscribe_id: None,
+            port_hopping,
+        })
+    }
+}
+
+/// `up`/`down` may be written as a number or as a string with unit.
+fn yaml_scalar(value: &Option<Value>) -> Option<String> {
+    match value {
+        Some(Value::Number(number)) => Some(number.to_string()),
+        Some(Value::String(text)) => Some(text.trim().to_string()),
+        Some(Value::Bool(flag)) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// Parse a Clash/Mihomo YAML config. Returns `None` if the text is not a
+/// Clash config; proxies of unsupported types or with missing fields are skipped.
+pub fn parse_clash_config(text: &str) -> Option<SubscriptionNodes> {
+    let config: ClashConfig = serde_yaml::from_str(text).ok()?;
+    let mut nodes = SubscriptionNodes::default();
+    for (index, value) in config.proxies.into_iter().enumerate() {
+        nodes.report.total_lines += 1;
+        let name = value
+            .get("name")
+            .and_then(|x| x.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let proxy = match serde_yaml::from_value::<ClashProxy>(value) {
+            Ok(proxy) => proxy,
+            Err(e) => {
+                nodes.report.skip(index + 1, &name, e);
+                continue;
+            }
+        };
+        let result = match proxy.r#type.as_str() {
+            "vless" | "vmess" | "trojan" => proxy
+                .r#type
+                .parse::<Protocol>()
+                .and_then(|protocol| proxy.to_xray(protocol))
+                .map(|model| nodes.push_xray(model)),
+            "ss" => proxy.to_shadowsocks().map(|model| nodes.push_xray(model)),
+            "tuic" => proxy.to_tuic().map(|model| nodes.push_xray(model)),
+            "hysteria2" => proxy.to_hysteria().map(|model| nodes.push_hysteria(model)),
+            other => Err(anyhow::anyhow!("unsupported proxy type: {other}")),
+        };
+        if let Err(e) = result {
+            nodes.report.skip(index + 1, &name, e);
+        }
+    }
+    Some(nodes)
+}
+
+/// Clash/Mihomo proxy entry of an xray node, `None` if its transport has no Clash
+/// equivalent.
+fn xray_to_clash(model: &xray::Model, name: &str) -> Option<JsonValue> {
+    let params: HashMap<&str, String> = model
+        .stream_settings()
+        .to_query_params()
+        .into_iter()
+        .collect();
+    let param = |key: &str| params.get(key).cloned();
+    let network = param("type").unwrap_or_else(|| "tcp".into());
+    if network == "xhttp" {
+        log::warn!("Skipping {} in the Clash export: XHTTP is not supported", name);
+        return None;
+    }
+    let r#type = match model.protocol {
+        Protocol::Shadowsocks => "ss".to_string(),
+        _ => model.protocol.to_value(),
+    };
+    let mut proxy = json!({
+        "name": name,
+        "type": r#type,
+        "server": bare_host(&model.address),
+        "port": model.port,
+        "udp": true,
+    });
+    match model.protocol {
+        Protocol::Vless => {
+            proxy["uuid"] = json!(model.uuid);
+            proxy["flow"] = json!(model.flow);
+        }
+        Protocol::Vmess => {
+            proxy["uuid"] = json!(model.uuid);
+            proxy["alterId"] = json!(0);
+            proxy["cipher"] = json!("auto");
+        }
+        Protocol::Trojan => proxy["password"] = json!(model.uuid),
+        Protocol::Shadowsocks => {
+            let settings = model.shadowsocks.as_ref();
+            proxy["password"] = json!(model.uuid);
+            proxy["cipher"] = json!(settings.map(|x| x.method.clone()));
+            proxy["plugin"] = json!(settings.and_then(|x| x.plugin.clone()));
+            let plugin_opts: Option<HashMap<&str, &str>> = settings
+                .and_then(|x| x.plugin_opts.as_deref())
+                .map(|opts| {
+                    opts.split(';')
+                        .filter_map(|x| x.split_once('='))
+                        .collect()
+                });
+            proxy["plugin-opts"] = json!(plugin_opts);
+        }
+        Protocol::Tuic => {
+            let settings = model.tuic.as_ref();
+            proxy["uuid"] = json!(model.uuid);
+            proxy["password"] = json!(settings.map(|x| x.password.clone()));
+            proxy["congestion-controller"] = json!(settings.map(|x| x.congestion_control.clone()));
+            proxy["udp-relay-mode"] = json!(settings.map(|x| x.udp_relay_mode.clone()));
+        }
+    }
+    let clash_network = match network.as_str() {
+        "http" => "h2",
+        "httpupgrade" => "ws",
+        network => network,
+    };
+    if clash_network != "tcp" {
+        proxy["network"] = json!(clash_network);
+    }
+    if let Some(security) = param("security") {
+        if !matches!(model.protocol, Protocol::Trojan | Protocol::Tuic) {
+            proxy["tls"] = json!(true);
+        }
+        let sni_key = match model.protocol {
+            Protocol::Trojan | Protocol::Tuic => "sni",
+            _ => "servername",
+        };
+        proxy[sni_key] = json!(param("sni"));
+        proxy["client-fingerprint"] = json!(param("fp"));
+        proxy["alpn"] = json!(param("alpn").map(|x| x.split(',').map(String::from).collect::<Vec<_>>()));
+        if params.contains_key("allowInsecure") {
+            proxy["skip-cert-verify"] = json!(true);
+        }
+        if security == "reality" {
+            proxy["reality-opts"] = json!({
+                "public-key": param("pbk"),
+                "short-id": param("sid"),
+            });
+        }
+    }
+    match network.as_str() {
+        "ws" => {
+            proxy["ws-opts"] = json!({
+                "path": param("path"),
+                "headers": param("host").map(|host| json!({ "Host": host })),
+            });
+        }
+        "httpupgrade" => {
+            proxy["ws-opts"] = json!({
+                "path": param("path"),
+                "headers": param("host").map(|host| json!({ "Host": host })),
+                "v2ray-http-upgrade": true,
+            });
+        }
+        "http" => {
+            proxy["h2-opts"] = json!({
+                "host": param("host").map(|x| x.split(',').map(String::from).collect::<Vec<_>>()),
+                "path": param("path"),
+            });
+        }
+        "grpc" => {
+            proxy["grpc-opts"] = json!({ "grpc-service-name": param("serviceName") });
+        }
+        _ => {}
+    }
+    Some(proxy)
+}
+
+/// Clash/Mihomo proxy entry of a Hysteria2 node.
+fn hysteria_to_clash(model: &hysteria::Model, name: &str) -> JsonValue {
+    let (server, port) = model.host_port();
+    let bandwidth = |value: &str| Some(value.to_string()).filter(|x| !x.is_empty());
+    json!({
+        "name": name,
+        "type": "hysteria2",
+        "server": server,
+        "port": port,
+        "password": model.auth,
+        "sni": model.tls.sni,
+        "skip-cert-verify": model.tls.insecure,
+        "obfs": model.obfs.as_ref().map(|x| x.r#type.clone()),
+        "obfs-password": model.obfs.as_ref().map(|x| x.password.clone()),
+        "ports": model.port_hopping.as_ref().map(|x| x.ports.clone()),
+        "hop-interval": model.port_hopping.as_ref().and_then(|x| x.interval),
+        "fingerprint": model.tls.pin_sha256,
+        "ca": model.tls.ca,
+        "up": bandwidth(&model.bandwidth.up),
+        "down": bandwidth(&model.bandwidth.down),
+    })
+}
+
+/// Render nodes as a Clash/Mihomo profile with a single selector group.
+pub fn export_clash_config(nodes: &SubscriptionNodes) -> anyhow::Result<String> {
+    let all_names = unique_names(nodes);
+    let (mut proxies, mut names): (Vec<JsonValue>, Vec<String>) = nodes
+        .xray
+        .iter()
+        .zip(&all_names)
+        .filter_map(|(model, name)| xray_to_clash(model, name).map(|x| (x, name.clone())))
+        .unzip();
+    for (model, name) in nodes.hysteria.iter().zip(&all_names[nodes.xray.len()..]) {
+        proxies.push(hysteria_to_clash(model, name));
+        names.push(name.clone());
+    }
+    let mut config = json!({
+        "proxies": proxies,
+        "proxy-groups": [{ "name": "Kitty", "type": "select", "proxies": names }],
+        "rules": ["MATCH,Kitty"],
+    });
+    strip_nulls(&mut config);
+    Ok(serde_yaml::to_string(&config)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLASH_CONFIG: &str = r#"
+port: 7890
+proxies:
+  - name: "vless-reality"
+    type: vless
+    server: example.com
+    port: 443
+    uuid: 0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a
+    flow: xtls-rprx-vision
+    network: grpc
+    tls: true
+    servername: www.microsoft.com
+    grpc-opts:
+      grpc-service-name: grpc-svc
+    reality-opts:
+      public-key: pubkey
+      short-id: abcd
+  - name: "vmess-ws"
+    type: vmess
+    server: 1.2.3.4
+    port: 8080
+    uuid: 0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a
+    alterId: 0
+    cipher: auto
+    network: ws
+    ws-opts:
+      path: /ws
+      headers:
+        Host: cdn.example.com
+  - name: "trojan"
+    type: trojan
+    server: trojan.example.com
+    port: 443
+    password: secret
+    sni: trojan.example.com
+    skip-cert-verify: true
+  - name: "hy2"
+    type: hysteria2
+    server: hy2.example.com
+    port: 8443
+    password: hy2pass
+    obfs: salamander
+    obfs-password: obfspass
+    ports: 20000-30000
+    hop-interval: 30
+    up: 50
+    down: "100 Mbps"
+  - name: "ss"
+    type: ss
+    server: ss.example.com
+    port: 8388
+    cipher: aes-128-gcm
+    password: pass
+  - name: "tuic"
+    type: tuic
+    server: tuic.example.com
+    port: 8443
+    uuid: 0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a
+    password: tuicpass
+    congestion-controller: cubic
+    udp-relay-mode: quic
+    alpn: [h3]
+    sni: tuic.example.com
+  - name: "vless-h2"
+    type: vless
+    server: h2.example.com
+    port: 443
+    uuid: 0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a
+    network: h2
+    tls: true
+    servername: h2.example.com
+    h2-opts:
+      host: [a.example.com, b.example.com]
+      path: /h2
+  - name: "vless-httpupgrade"
+    type: vless
+    server: up.example.com
+    port: 443
+    uuid: 0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a
+    network: ws
+    tls: true
+    ws-opts:
+      path: /up
+      headers:
+        Host: cdn.example.com
+      v2ray-http-upgrade: true
+"#;
+
+    #[test]
+    fn test_parse_clash_config() {
+        let nodes = parse_clash_config(CLASH_CONFIG).unwrap();
+        assert_eq!(nodes.xray.len(), 7);
+        assert_eq!(nodes.hysteria.len(), 1);
+
+        let vless = serde_json::to_value(&nodes.xray[0]).unwrap();
+        assert_eq!(vless["name"], "vless-reality");
+        assert_eq!(vless["stream_settings"]["network"], "grpc");
+        assert_eq!(vless["flow"], "xtls-rprx-vision");
+        assert_eq!(vless["stream_settings"]["security"], "reality");
+        assert_eq!(
+            vless["stream_settings"]["realitySettings"]["shortId"],
+            "abcd"
+        );
+
+        let vmess = serde_json::to_value(&nodes.xray[1]).unwrap();
+        assert_eq!(vmess["stream_settings"]["network"], "ws");
+        assert_eq!(vmess["stream_settings"]["wsSettings"]["path"], "/ws");
+
+        assert_eq!(nodes.xray[2].uuid, "secret");
+
+        let hy2 = &nodes.hysteria[0];
+        assert_eq!(hy2.server, "hy2.example.com:8443");
+        assert_eq!(hy2.bandwidth.up, "50 mbps");
+        assert_eq!(hy2.bandwidth.down, "100 Mbps");
+        assert_eq!(hy2.obfs.as_ref().unwrap().password, "obfspass");
+        let hopping = hy2.port_hopping.as_ref().unwrap();
+        assert_eq!((hopping.ports.as_str(), hopping.interval), ("20000-30000", Some(30)));
+
+        let ss = &nodes.xray[3];
+        assert_eq!(ss.protocol, Protocol::Shadowsocks);
+        assert_eq!(ss.uuid, "pass");
+        assert_eq!(ss.shadowsocks.as_ref().unwrap().method, "aes-128-gcm");
+
+        let tuic = &nodes.xray[4];
+        assert_eq!(tuic.protocol, Protocol::Tuic);
+        let settings = tuic.tuic.as_ref().unwrap();
+        assert_eq!(settings.password, "tuicpass");
+        assert_eq!(settings.congestion_control, "cubic");
+        assert_eq!(settings.udp_relay_mode, "quic");
+        let tls = tuic.stream_settings().tls_settings().unwrap();
+        assert_eq!(tls.alpn(), ["h3"]);
+
+        let h2 = nodes.xray[5].stream_settings();
+        assert_eq!(h2.network(), "http");
+        assert_eq!(h2.http_path(), Some("/h2"));
+        let upgrade = nodes.xray[6].stream_settings();
+        assert_eq!(upgrade.network(), "httpupgrade");
+        assert_eq!(upgrade.http_host(), Some("cdn.example.com"));
+    }
+
+    #[test]
+    fn test_export_clash_config_round_trip() {
+        let nodes = parse_clash_config(CLASH_CONFIG).unwrap();
+        let exported = export_clash_config(&nodes).unwrap();
+        let parsed = parse_clash_config(&exported).unwrap();
+        assert!(parsed.report.skipped.is_empty());
+        assert_eq!(parsed.xray, nodes.xray);
+        assert_eq!(parsed.hysteria, nodes.hysteria);
+
+        let config: Value = serde_yaml::from_str(&exported).unwrap();
+        assert_eq!(config["proxy-groups"][0]["proxies"][7], "hy2");
+        assert!(config["proxies"][0]["plugin"].is_null());
+    }
+
+    #[test]
+    fn test_export_skips_xhttp() {
+        use crate::apis::parse_subscription::parse_subscription_text;
+
+        let nodes = parse_subscription_text(
+            "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=xhttp&security=tls&sni=a.example.com&path=%2Fx#X\n\
+             vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@b.example.com:443?type=tcp#T",
+        );
+        let exported = export_clash_config(&nodes).unwrap();
+        let config: Value = serde_yaml::from_str(&exported).unwrap();
+        assert_eq!(config["proxies"].as_sequence().unwrap().len(), 1);
+        assert_eq!(config["proxies"][0]["name"], "T");
+        assert_eq!(
+            config["proxy-groups"][0]["proxies"].as_sequence().unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_base64_body_is_not_clash() {
+        assert!(parse_clash_config("dmxlc3M6Ly8=").is_none());
+        assert!(parse_clash_config("vless://uuid@host:443#name").is_none());
+    }
+}
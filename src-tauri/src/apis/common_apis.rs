@@ -96,6 +96,10 @@ impl CommonAPI {
                     mode: "Rules".to_string(),
                     update_interval: 3,
                     log_level: "debug".to_string(),
+                    share_server_enabled: false,
+                    share_server_port: 10088,
+                    share_server_token: "".to_string(),
+                    skip_duplicate_nodes: false,
                 };
                 let inserted = default_config.insert_one(db).await?;
                 KittyResponse::<base_config::Model>::from_data(inserted)
@@ -111,6 +115,16 @@ impl CommonAPI {
     where
         C: ConnectionTrait,
     {
+        let mut record = record;
+        // The share server and duplicate node settings have their own commands;
+        // the settings page only holds a cached copy of them, so keep what the
+        // database has.
+        if let Some(current) = base_config::Model::first(db).await? {
+            record.share_server_enabled = current.share_server_enabled;
+            record.share_server_port = current.share_server_port;
+            record.share_server_token = current.share_server_token;
+            record.skip_duplicate_nodes = current.skip_duplicate_nodes;
+        }
         let updated_record = record.update(db).await?;
         Ok(KittyResponse::<base_config::Model>::from_data(
             updated_record,
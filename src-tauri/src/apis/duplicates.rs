@@ -0,0 +1,176 @@
+use anyhow::Result;
+use entity::{hysteria, xray};
+use sea_orm::ConnectionTrait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateNode {
+    pub id: i32,
+    pub name: String,
+    pub subscribe_id: Option<i32>,
+}
+
+/// Enabled nodes sharing a fingerprint. The first node is the one kept by a merge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// `xray` or `hysteria`, as in `Proxy`
+    pub proxy_type: String,
+    pub fingerprint: String,
+    pub nodes: Vec<DuplicateNode>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub groups: Vec<DuplicateGroup>,
+    /// Nodes deleted by the merge, 0 when only reporting
+    pub removed: usize,
+}
+
+/// Group nodes by fingerprint, keeping the groups with more than one node.
+///
+/// Inside a group manual nodes come first, then the oldest node, unless `keep_id`
+/// (e.g. the active proxy) is a member.
+fn group_duplicates(
+    proxy_type: &str,
+    nodes: impl Iterator<Item = (String, DuplicateNode)>,
+    keep_id: Option<i32>,
+) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (fingerprint, node) in nodes {
+        match index.get(&fingerprint) {
+            Some(&i) => groups[i].nodes.push(node),
+            None => {
+                index.insert(fingerprint.clone(), groups.len());
+                groups.push(DuplicateGroup {
+                    proxy_type: proxy_type.to_string(),
+                    fingerprint,
+                    nodes: vec![node],
+                });
+            }
+        }
+    }
+    groups.retain(|group| group.nodes.len() > 1);
+    for group in groups.iter_mut() {
+        group
+            .nodes
+            .sort_by_key(|x| (Some(x.id) != keep_id, x.subscribe_id.is_some(), x.id));
+    }
+    groups
+}
+
+/// Find the enabled nodes that point to the same server with the same credentials
+/// and transport, whether they come from different subscriptions or were added
+/// manually. `active` is the `(proxy_type, id)` of the node to keep if duplicated.
+pub async fn find_duplicates<C>(db: &C, active: Option<(&str, i32)>) -> Result<Vec<DuplicateGroup>>
+where
+    C: ConnectionTrait,
+{
+    let keep_id = |proxy_type: &str| active.filter(|x| x.0 == proxy_type).map(|x| x.1);
+    let xray_nodes = xray::Model::fetch_enabled(db).await?.into_iter().map(|x| {
+        let node = DuplicateNode {
+            id: x.id,
+            name: x.name.clone(),
+            subscribe_id: x.subscribe_id,
+        };
+        (x.fingerprint(), node)
+    });
+    let mut groups = group_duplicates("xray", xray_nodes, keep_id("xray"));
+    let hysteria_nodes = hysteria::Model::fetch_enabled(db)
+        .await?
+        .into_iter()
+        .map(|x| {
+            let node = DuplicateNode {
+                id: x.id,
+                name: x.name.clone(),
+                subscribe_id: x.subscribe_id,
+            };
+            (x.fingerprint(), node)
+        });
+    groups.extend(group_duplicates(
+        "hysteria",
+        hysteria_nodes,
+        keep_id("hysteria"),
+    ));
+    Ok(groups)
+}
+
+/// Delete all but the first node of every group, returning the number deleted.
+///
+/// Subscription nodes come back on the next refresh unless `skip_duplicate_nodes` is
+/// enabled in the base config.
+pub async fn merge_duplicates<C>(db: &C, groups: &[DuplicateGroup]) -> Result<usize>
+where
+    C: ConnectionTrait,
+{
+    let mut xray_ids = Vec::new();
+    let mut hysteria_ids = Vec::new();
+    for group in groups {
+        let ids = group.nodes.iter().skip(1).map(|x| x.id);
+        match group.proxy_type.as_str() {
+            "xray" => xray_ids.extend(ids),
+            _ => hysteria_ids.extend(ids),
+        }
+    }
+    let removed = xray_ids.len() + hysteria_ids.len();
+    xray::Model::delete_by_ids(db, xray_ids).await?;
+    hysteria::Model::delete_by_ids(db, hysteria_ids).await?;
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::parse_subscription::parse_subscription_text;
+    use crate::apis::test_utils::setup_db;
+    use entity::base_config;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    #[tokio::test]
+    async fn test_find_and_merge_duplicates() {
+        let db = setup_db(&["https://a.example.com/sub", "https://b.example.com/sub"]).await;
+        let first = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@A.example.com:443?type=ws&path=%2Fws#HK\n\
+                     hy2://secret@c.example.com#HY";
+        // Same servers under other names, plus a different ws path
+        let second = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=ws&path=%2Fws&security=tls#HK%20copy\n\
+                      vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=ws&path=%2Fother#HK%20other\n\
+                      hy2://secret@c.example.com:443#HY%20copy";
+        parse_subscription_text(first).sync(&db, 1).await.unwrap();
+        parse_subscription_text(second).sync(&db, 2).await.unwrap();
+
+        let groups = find_duplicates(&db, None).await.unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].proxy_type, "xray");
+        let names: Vec<&str> = groups[0].nodes.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["HK", "HK copy"]);
+        assert_eq!(groups[1].nodes.len(), 2);
+
+        // The active proxy is kept
+        let groups = find_duplicates(&db, Some(("hysteria", 2))).await.unwrap();
+        assert_eq!(groups[1].nodes[0].name, "HY copy");
+        assert_eq!(merge_duplicates(&db, &groups).await.unwrap(), 2);
+        assert!(find_duplicates(&db, None).await.unwrap().is_empty());
+        assert_eq!(
+            hysteria::Model::fetch_all(&db).await.unwrap()[0].name,
+            "HY copy"
+        );
+
+        // Re-importing the deleted copy is skipped once the option is on
+        base_config::Model::update_sysproxy_flag(&db, false)
+            .await
+            .unwrap();
+        let mut config: base_config::ActiveModel = base_config::Model::first(&db)
+            .await
+            .unwrap()
+            .unwrap()
+            .into();
+        config.skip_duplicate_nodes = Set(true);
+        config.update(&db).await.unwrap();
+        let mut nodes = parse_subscription_text(first);
+        nodes.skip_duplicates(&db, Some(1)).await.unwrap();
+        assert_eq!(nodes.report.duplicates, 1);
+        assert_eq!(nodes.xray.len(), 1);
+        assert!(nodes.hysteria.is_empty());
+    }
+}
@@ -1,55 +1,1041 @@
-use base64::{engine::general_purpose, DecodeError, Engine};
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use chrono::{DateTime, Utc};
 use entity::types::ProtocolLine;
+use entity::subscribe::{FetchMode, NodeFilters, RequestHeaders, SubscriptionKind};
+use entity::{base_config, hysteria, subscribe, subscription_snapshot, xray};
 use reqwest;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::StatusCode;
 use anyhow::anyhow;
+use regex::Regex;
+use sea_orm::{ActiveEnum, ConnectionTrait, DbErr, Set, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::AddAssign;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use std::time::Duration;
 
-fn safe_decode_base64(text: &str, no_pad: bool) -> String {
-    // let trimmed_text = text.trim();
-    let bytes_data = if !no_pad {
-        general_purpose::STANDARD.decode(text)
-    } else {
-        general_purpose::STANDARD_NO_PAD.decode(text)
-    };
+use super::clash::parse_clash_config;
+use super::singbox::parse_singbox_config;
+
+/// Number of nodes added, updated and removed by a subscription refresh.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionChanges {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl AddAssign for SubscriptionChanges {
+    fn add_assign(&mut self, other: Self) {
+        self.added += other.added;
+        self.updated += other.updated;
+        self.removed += other.removed;
+    }
+}
+
+/// A line (or Clash / sing-box proxy entry) that could not be imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedLine {
+    /// 1-based line number, or entry index for Clash / sing-box configs
+    pub line: usize,
+    pub content: String,
+    pub reason: String,
+}
+
+/// Outcome of importing or refreshing a subscription.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub subscribe_id: Option<i32>,
+    /// Non-empty, non-comment lines, or proxy entries of a Clash / sing-box config
+    pub total_lines: usize,
+    /// Parsed nodes by protocol
+    pub parsed: BTreeMap<String, usize>,
+    pub skipped: Vec<SkippedLine>,
+    /// Parsed nodes dropped by the subscription's filters
+    pub filtered: usize,
+    /// Parsed nodes dropped as duplicates, see `SubscriptionNodes::skip_duplicates`
+    pub duplicates: usize,
+    pub changes: SubscriptionChanges,
+    /// The body didn't change since the last refresh, nothing was parsed
+    pub not_modified: bool,
+}
+
+impl ImportReport {
+    pub fn skip(&mut self, line: usize, content: &str, reason: impl ToString) {
+        self.skipped.push(SkippedLine {
+            line,
+            content: content.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+/// Nodes parsed from a subscription, grouped by the table they are stored in.
+#[derive(Debug, Default)]
+pub struct SubscriptionNodes {
+    pub xray: Vec<xray::Model>,
+    pub hysteria: Vec<hysteria::Model>,
+    pub report: ImportReport,
+}
+
+impl SubscriptionNodes {
+    /// Parse a list of share links, recording every line that can't be converted.
+    pub fn from_share_links(text: &str) -> Self {
+        let mut nodes = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            nodes.push_link(index + 1, line);
+        }
+        nodes
+    }
+
+    /// Pull every supported share link out of arbitrary text, e.g. a chat message
+    /// pasted from the clipboard, including links inside base64 blobs. Lines without
+    /// a link are ignored; links that fail to parse are reported with their line.
+    pub fn from_text(text: &str) -> Self {
+        let mut nodes = Self::default();
+        let mut links = extract_share_links(text, None);
+        if links.is_empty() {
+            // A whole base64 subscription body, possibly wrapped over several lines
+            let decoded = safe_decode_base64(text.trim());
+            if decoded != text.trim() {
+                links = extract_share_links(&decoded, None);
+            }
+        }
+        for (line, link) in links {
+            nodes.push_link(line, &link);
+        }
+        nodes
+    }
+
+    /// Parse one share link, recording it as skipped if it can't be converted.
+    fn push_link(&mut self, line: usize, link: &str) {
+        self.report.total_lines += 1;
+        // Keep the `#fragment`: it carries the node name
+        let Some((protocol, _)) = link.split_once("://") else {
+            self.report.skip(line, link, "not a share link");
+            return;
+        };
+        let protocol_line = ProtocolLine::new(link.to_string(), protocol.into());
+        let result = if protocol_line.is_xray() {
+            xray::Model::from_str(link).map(|model| self.push_xray(model))
+        } else if protocol_line.is_hy2() {
+            hysteria::Model::from_str(link).map(|model| self.push_hysteria(model))
+        } else {
+            Err(anyhow!("unsupported protocol: {protocol}"))
+        };
+        if let Err(e) = result {
+            self.report.skip(line, link, e);
+        }
+    }
+
+    pub fn push_xray(&mut self, model: xray::Model) {
+        *self
+            .report
+            .parsed
+            .entry(model.protocol.to_value())
+            .or_default() += 1;
+        self.xray.push(model);
+    }
+
+    pub fn push_hysteria(&mut self, model: hysteria::Model) {
+        *self.report.parsed.entry("hysteria2".into()).or_default() += 1;
+        self.hysteria.push(model);
+    }
+
+    /// Take the report out, e.g. before the nodes are consumed by `sync`.
+    pub fn take_report(&mut self) -> ImportReport {
+        std::mem::take(&mut self.report)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xray.is_empty() && self.hysteria.is_empty()
+    }
+
+    /// Drop and rename nodes according to the subscription's filters.
+    pub fn apply_filters(&mut self, filters: &NodeFilters) -> anyhow::Result<()> {
+        let compile = |pattern: &Option<String>| -> anyhow::Result<Option<Regex>> {
+            match pattern.as_deref().map(str::trim).filter(|x| !x.is_empty()) {
+                Some(pattern) => Ok(Some(
+                    Regex::new(pattern).map_err(|e| anyhow!("invalid filter regex: {e}"))?,
+                )),
+                None => Ok(None),
+            }
+        };
+        let include = compile(&filters.include)?;
+        let exclude = compile(&filters.exclude)?;
+        let renames = filters
+            .renames
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map(|regex| (regex, rule.replacement.as_str()))
+                    .map_err(|e| anyhow!("invalid rename regex: {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let protocols: Vec<String> = filters.protocols.iter().map(|x| x.to_lowercase()).collect();
+
+        let keep = |name: &str, protocol: &str| {
+            (protocols.is_empty() || protocols.iter().any(|x| x == protocol))
+                && include.as_ref().is_none_or(|x| x.is_match(name))
+                && !exclude.as_ref().is_some_and(|x| x.is_match(name))
+        };
+        let rename = |name: &mut String| {
+            for (regex, replacement) in renames.iter() {
+                *name = regex.replace_all(name, *replacement).into_owned();
+            }
+        };
+
+        let before = self.xray.len() + self.hysteria.len();
+        self.xray
+            .retain(|record| keep(&record.name, &record.protocol.to_value()));
+        self.hysteria.retain(|record| keep(&record.name, "hysteria2"));
+        self.report.filtered += before - self.xray.len() - self.hysteria.len();
+        for record in self.xray.iter_mut() {
+            rename(&mut record.name);
+        }
+        for record in self.hysteria.iter_mut() {
+            rename(&mut record.name);
+        }
+        Ok(())
+    }
+
+    /// When `skip_duplicate_nodes` is enabled in the base config, drop the nodes whose
+    /// fingerprint matches an enabled node stored outside the given subscription
+    /// (`None` for manual imports) or an earlier node of the same import.
+    pub async fn skip_duplicates<C>(&mut self, db: &C, subscribe_id: Option<i32>) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let enabled = base_config::Model::first(db)
+            .await?
+            .is_some_and(|x| x.skip_duplicate_nodes);
+        if !enabled {
+            return Ok(());
+        }
+        let mut seen = HashSet::new();
+        for record in xray::Model::fetch_enabled(db).await? {
+            if subscribe_id.is_none() || record.subscribe_id != subscribe_id {
+                seen.insert(record.fingerprint());
+            }
+        }
+        for record in hysteria::Model::fetch_enabled(db).await? {
+            if subscribe_id.is_none() || record.subscribe_id != subscribe_id {
+                seen.insert(record.fingerprint());
+            }
+        }
+        let before = self.xray.len() + self.hysteria.len();
+        self.xray.retain(|record| seen.insert(record.fingerprint()));
+        self.hysteria.retain(|record| seen.insert(record.fingerprint()));
+        self.report.duplicates += before - self.xray.len() - self.hysteria.len();
+        Ok(())
+    }
+
+    pub fn set_subscribe_id(&mut self, subscribe_id: i32) {
+        for record in self.xray.iter_mut() {
+            record.subscribe_id = Some(subscribe_id);
+        }
+        for record in self.hysteria.iter_mut() {
+            record.subscribe_id = Some(subscribe_id);
+        }
+    }
+
+    pub async fn insert<C>(self, db: &C) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !self.xray.is_empty() {
+            xray::Model::insert_many(db, self.xray).await?;
+        }
+        if !self.hysteria.is_empty() {
+            hysteria::Model::insert_many(db, self.hysteria).await?;
+        }
+        Ok(())
+    }
+
+    /// Store the nodes as manually added ones, dropping duplicates when configured,
+    /// and return the import report.
+    pub async fn import_manual<C>(mut self, db: &C) -> Result<ImportReport, DbErr>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        self.skip_duplicates(db, None).await?;
+        let mut report = self.take_report();
+        report.changes.added = self.xray.len() + self.hysteria.len();
+
+        let txn = db.begin().await?;
+        self.insert(&txn).await?;
+        txn.commit().await?;
+        Ok(report)
+    }
+
+    /// Delete every node that belongs to the given subscription.
+    pub async fn delete_by_subscribe_id<C>(
+        db: &C,
+        subscribe_id: i32,
+    ) -> Result<SubscriptionChanges, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let xray_ids: Vec<i32> = xray::Model::fetch_by_subscribe_id(db, subscribe_id)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        let hysteria_ids: Vec<i32> = hysteria::Model::fetch_by_subscribe_id(db, subscribe_id)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        let removed = xray_ids.len() + hysteria_ids.len();
+        xray::Model::delete_by_ids(db, xray_ids).await?;
+        hysteria::Model::delete_by_ids(db, hysteria_ids).await?;
+        Ok(SubscriptionChanges {
+            removed,
+            ..Default::default()
+        })
+    }
+
+    /// Reconcile the stored nodes of a subscription with the freshly parsed ones.
+    ///
+    /// Nodes are matched by their identity (protocol, address, port, credential and
+    /// transport) so that unchanged servers keep their IDs: matches are updated in
+    /// place, new nodes inserted and nodes gone from the subscription deleted.
+    pub async fn sync<C>(mut self, db: &C, subscribe_id: i32) -> Result<SubscriptionChanges, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.set_subscribe_id(subscribe_id);
+        let mut changes = SubscriptionChanges::default();
+
+        let mut stale_ids = Vec::new();
+        let mut existing: HashMap<String, xray::Model> = HashMap::new();
+        for record in xray::Model::fetch_by_subscribe_id(db, subscribe_id).await? {
+            if let Some(duplicate) = existing.insert(record.identity(), record) {
+                stale_ids.push(duplicate.id);
+            }
+        }
+        let mut new_records = Vec::new();
+        for mut record in self.xray {
+            match existing.remove(&record.identity()) {
+                Some(old) => {
+                    record.id = old.id;
+                    if record != old {
+                        record.update(db).await?;
+                        changes.updated += 1;
+                    }
+                }
+                None => new_records.push(record),
+            }
+        }
+        stale_ids.extend(existing.into_values().map(|x| x.id));
+        changes.added += new_records.len();
+        changes.removed += stale_ids.len();
+        if !new_records.is_empty() {
+            xray::Model::insert_many(db, new_records).await?;
+        }
+        xray::Model::delete_by_ids(db, stale_ids).await?;
+
+        let mut stale_ids = Vec::new();
+        let mut existing: HashMap<String, hysteria::Model> = HashMap::new();
+        for record in hysteria::Model::fetch_by_subscribe_id(db, subscribe_id).await? {
+            if let Some(duplicate) = existing.insert(record.identity(), record) {
+                stale_ids.push(duplicate.id);
+            }
+        }
+        let mut new_records = Vec::new();
+        for mut record in self.hysteria {
+            match existing.remove(&record.identity()) {
+                Some(old) => {
+                    record.id = old.id;
+                    if record != old {
+                        record.update(db).await?;
+                        changes.updated += 1;
+                    }
+                }
+                None => new_records.push(record),
+            }
+        }
+        stale_ids.extend(existing.into_values().map(|x| x.id));
+        changes.added += new_records.len();
+        changes.removed += stale_ids.len();
+        if !new_records.is_empty() {
+            hysteria::Model::insert_many(db, new_records).await?;
+        }
+        hysteria::Model::delete_by_ids(db, stale_ids).await?;
+
+        Ok(changes)
+    }
+}
+
+/// Quota, expiry and refresh interval reported by the provider in response headers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SubscriptionMeta {
+    pub upload: Option<i64>,
+    pub download: Option<i64>,
+    pub total: Option<i64>,
+    pub expire_at: Option<DateTime<Utc>>,
+    pub update_interval: Option<i32>,
+}
 
-    match bytes_data {
-        Ok(decode_bytes) => String::from_utf8(decode_bytes).expect("Invalid UTF-8 sequence"),
-        Err(_e) => {
-            match _e {
-                DecodeError::InvalidPadding => safe_decode_base64(text, true),
-                _ => text.to_string(),
+impl SubscriptionMeta {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut meta = Self::default();
+        if let Some(userinfo) = headers
+            .get("subscription-userinfo")
+            .and_then(|x| x.to_str().ok())
+        {
+            meta.parse_userinfo(userinfo);
+        }
+        meta.update_interval = headers
+            .get("profile-update-interval")
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.trim().parse::<i32>().ok())
+            .filter(|x| *x > 0);
+        meta
+    }
+
+    /// Parse `upload=1234; download=5678; total=1099511627776; expire=1735689600`.
+    fn parse_userinfo(&mut self, value: &str) {
+        for pair in value.split(';') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            // some providers send floats or empty values
+            let Ok(value) = value.trim().parse::<f64>() else {
+                continue;
+            };
+            let value = value as i64;
+            match key.trim().to_lowercase().as_str() {
+                "upload" => self.upload = Some(value),
+                "download" => self.download = Some(value),
+                "total" => self.total = Some(value),
+                // `expire=0` means the subscription never expires
+                "expire" if value > 0 => self.expire_at = DateTime::from_timestamp(value, 0),
+                _ => {}
             }
-        },
+        }
+    }
+
+    pub fn apply(&self, model: &mut subscribe::ActiveModel) {
+        model.upload = Set(self.upload);
+        model.download = Set(self.download);
+        model.total = Set(self.total);
+        model.expire_at = Set(self.expire_at);
+        model.update_interval = Set(self.update_interval);
     }
 }
 
-pub async fn download_subcriptions(url: &str) -> anyhow::Result<Vec<ProtocolLine>> {
-    let client = reqwest::Client::builder()
-            .user_agent("OKZTWO-Mac-Client-1.5.6")
-            .build()?;
+/// `ETag` / `Last-Modified` of a previous download, used for conditional requests.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
 
-    let resp = client.get(url).send().await?;
-    let resp_text = if resp.status().is_success() {
-        resp.text().await?
+impl CacheValidators {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|x: &HeaderValue| x.to_str().ok())
+                .map(|x| x.trim().to_string())
+                .filter(|x| !x.is_empty())
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    pub fn from_snapshot(snapshot: &subscription_snapshot::Model) -> Self {
+        Self {
+            etag: snapshot.etag.clone(),
+            last_modified: snapshot.last_modified.clone(),
+        }
+    }
+
+    fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+const DEFAULT_USER_AGENT: &str = "OKZTWO-Mac-Client-1.5.6";
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Per-subscription HTTP settings used when downloading it.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FetchOptions {
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub timeout_secs: Option<i32>,
+    #[serde(default)]
+    pub fetch_mode: FetchMode,
+    /// Local HTTP proxy, resolved from `base_config` right before fetching.
+    #[serde(skip)]
+    pub proxy_url: Option<String>,
+    /// Validators of the last download; when set the request is conditional.
+    #[serde(skip)]
+    pub validators: CacheValidators,
+}
+
+impl FetchOptions {
+    pub fn from_model(model: &subscribe::Model) -> Self {
+        Self {
+            user_agent: model.user_agent.clone(),
+            headers: model.headers.clone().unwrap_or_default().0,
+            timeout_secs: model.timeout_secs,
+            fetch_mode: model.fetch_mode,
+            proxy_url: None,
+            validators: CacheValidators::default(),
+        }
+    }
+
+    pub fn apply(&self, model: &mut subscribe::ActiveModel) {
+        model.user_agent = Set(self.user_agent.clone().filter(|x| !x.trim().is_empty()));
+        model.headers = Set(if self.headers.is_empty() {
+            None
+        } else {
+            Some(RequestHeaders(self.headers.clone()))
+        });
+        model.timeout_secs = Set(self.timeout_secs.filter(|x| *x > 0));
+        model.fetch_mode = Set(self.fetch_mode);
+    }
+
+    /// Resolve the local HTTP proxy address when the subscription may be fetched through it.
+    pub async fn with_local_proxy<C>(mut self, db: &C) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if self.fetch_mode != FetchMode::Direct {
+            self.proxy_url = base_config::Model::first(db)
+                .await?
+                .map(|config| format!("http://127.0.0.1:{}", config.http_port));
+        }
+        Ok(self)
+    }
+
+    /// Load the options of a stored subscription, ready to fetch with.
+    pub async fn for_subscription<C>(db: &C, model: &subscribe::Model) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Self::from_model(model).with_local_proxy(db).await
+    }
+
+    fn build_client(&self, proxy_url: Option<&str>) -> anyhow::Result<reqwest::Client> {
+        let mut headers = HeaderMap::new();
+        for (key, value) in self.headers.iter() {
+            headers.insert(
+                HeaderName::from_bytes(key.trim().as_bytes())?,
+                HeaderValue::from_str(value.trim())?,
+            );
+        }
+        let user_agent = self
+            .user_agent
+            .as_deref()
+            .filter(|x| !x.trim().is_empty())
+            .unwrap_or(DEFAULT_USER_AGENT);
+        let timeout = self
+            .timeout_secs
+            .filter(|x| *x > 0)
+            .map(|x| x as u64)
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        let builder = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .default_headers(headers)
+            .timeout(Duration::from_secs(timeout));
+        let builder = match proxy_url {
+            Some(proxy_url) => builder.proxy(reqwest::Proxy::all(proxy_url)?),
+            None => builder.no_proxy(),
+        };
+        Ok(builder.build()?)
+    }
+}
+
+/// Raw body of a subscription together with the metadata from its headers.
+pub struct SubscriptionDownload {
+    pub text: String,
+    pub meta: SubscriptionMeta,
+    pub validators: CacheValidators,
+    /// The server answered `304 Not Modified`; `text` is empty.
+    pub not_modified: bool,
+}
+
+const LENIENT_BASE64: GeneralPurposeConfig = GeneralPurposeConfig::new()
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+    .with_decode_allow_trailing_bits(true);
+const BASE64_ENGINES: [GeneralPurpose; 2] = [
+    GeneralPurpose::new(&alphabet::STANDARD, LENIENT_BASE64),
+    GeneralPurpose::new(&alphabet::URL_SAFE, LENIENT_BASE64),
+];
+
+/// Decode a base64 subscription body in the standard or URL-safe alphabet, with or
+/// without padding. Whitespace (wrapped lines) is ignored and invalid UTF-8 replaced;
+/// text that isn't base64 is returned unchanged.
+fn safe_decode_base64(text: &str) -> String {
+    let compact: String = text.chars().filter(|x| !x.is_whitespace()).collect();
+    for engine in BASE64_ENGINES.iter() {
+        if let Ok(bytes) = engine.decode(&compact) {
+            return String::from_utf8_lossy(&bytes).into_owned();
+        }
+    }
+    text.to_string()
+}
+
+/// Anything that looks like `scheme://...` up to whitespace, quotes or brackets.
+static LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"[A-Za-z][A-Za-z0-9+.-]*://[^\s"'<>`]+"#).unwrap());
+/// A standalone base64 token long enough to hold a share link.
+static BLOB_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9+/_-]{16,}={0,2}$").unwrap());
+
+/// Share links of supported protocols found in `text`, with their 1-based line
+/// number. Links inside base64 blobs are attributed to the blob's line, or to
+/// `line` when decoding a blob found on that line.
+fn extract_share_links(text: &str, line: Option<usize>) -> Vec<(usize, String)> {
+    let mut links = Vec::new();
+    for (index, content) in text.lines().enumerate() {
+        let line = line.unwrap_or(index + 1);
+        for found in LINK_REGEX.find_iter(content) {
+            let link = found.as_str();
+            let protocol = link.split_once("://").map_or("", |x| x.0).to_lowercase();
+            let protocol_line = ProtocolLine::new(link.to_string(), protocol);
+            if protocol_line.is_xray() || protocol_line.is_hy2() {
+                links.push((line, link.to_string()));
+            }
+        }
+        for token in content.split_whitespace() {
+            if !BLOB_REGEX.is_match(token) {
+                continue;
+            }
+            let decoded = safe_decode_base64(token);
+            if decoded != token && decoded.contains("://") {
+                links.extend(extract_share_links(&decoded, Some(line)));
+            }
+        }
+    }
+    links
+}
+
+/// Parse a subscription body, which is either a sing-box JSON config, a Clash YAML
+/// config or a list of share links.
+pub fn parse_subscription_text(text: &str) -> SubscriptionNodes {
+    if let Some(nodes) = parse_singbox_config(text) {
+        return nodes;
+    }
+    if let Some(nodes) = parse_clash_config(text) {
+        return nodes;
+    }
+    SubscriptionNodes::from_share_links(&safe_decode_base64(text.trim()))
+}
+
+async fn fetch_subscription(
+    url: &str,
+    options: &FetchOptions,
+    proxy_url: Option<&str>,
+) -> anyhow::Result<SubscriptionDownload> {
+    let client = options.build_client(proxy_url)?;
+    let resp = options.validators.apply(client.get(url)).send().await?;
+    let not_modified = resp.status() == StatusCode::NOT_MODIFIED;
+    if !not_modified && !resp.status().is_success() {
+        return Err(anyhow!("download subscriptions failed."));
+    }
+    let meta = SubscriptionMeta::from_headers(resp.headers());
+    let validators = CacheValidators::from_headers(resp.headers());
+    let text = if not_modified {
+        String::new()
     } else {
-        return Err(anyhow!("download subscriptions failed.").into());
+        resp.text().await?
     };
+    anyhow::Ok(SubscriptionDownload {
+        text,
+        meta,
+        validators,
+        not_modified,
+    })
+}
 
-    let decoded_text = safe_decode_base64(&resp_text, false);
-    let mut results = Vec::new();
-    for line in decoded_text.lines() {
-        let line = line.trim();
-        if line.trim().starts_with("#") {
-            continue;
-        }
-        if line.contains("://") {
-            let trimed_line = if let Some(pos) = line.rfind('#') {
-                &line[..pos]
-            } else {
-                line
-            };
-            let protocol = trimed_line.split("://").next().unwrap();
-            results.push(ProtocolLine::new(trimed_line.to_string(), protocol.into()))
+pub async fn download_subcriptions(
+    url: &str,
+    options: &FetchOptions,
+) -> anyhow::Result<SubscriptionDownload> {
+    let proxy_url = options.proxy_url.as_deref();
+    match options.fetch_mode {
+        FetchMode::Direct => fetch_subscription(url, options, None).await,
+        FetchMode::Proxy => {
+            let proxy_url = proxy_url.ok_or_else(|| anyhow!("local proxy is not configured"))?;
+            fetch_subscription(url, options, Some(proxy_url)).await
+        }
+        FetchMode::Auto => {
+            if let Some(proxy_url) = proxy_url {
+                match fetch_subscription(url, options, Some(proxy_url)).await {
+                    Ok(download) => return anyhow::Ok(download),
+                    Err(e) => log::warn!("Fetching {} through local proxy failed: {}", url, e),
+                }
+            }
+            fetch_subscription(url, options, None).await
+        }
+    }
+}
+
+/// Where a subscription body is loaded from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionSource {
+    Remote(String),
+    File(PathBuf),
+    Text(String),
+}
+
+impl SubscriptionSource {
+    /// `content` makes a pasted-text subscription, otherwise the URL scheme decides.
+    pub fn new(url: &str, content: Option<String>) -> anyhow::Result<Self> {
+        if let Some(content) = content {
+            return anyhow::Ok(Self::Text(content));
+        }
+        match SubscriptionKind::from_url(url) {
+            Some(SubscriptionKind::Remote) => anyhow::Ok(Self::Remote(url.trim().to_string())),
+            Some(SubscriptionKind::File) => {
+                let path = reqwest::Url::parse(url.trim())
+                    .ok()
+                    .and_then(|x| x.to_file_path().ok())
+                    .ok_or_else(|| anyhow!("Invalid file URL: {}", url))?;
+                anyhow::Ok(Self::File(path))
+            }
+            _ => Err(anyhow!("Only HTTP/HTTPS and file:// subscription URLs are supported")),
         }
     }
-    anyhow::Ok(results)
+
+    pub fn from_model(model: &subscribe::Model) -> anyhow::Result<Self> {
+        match model.kind {
+            SubscriptionKind::Text => {
+                anyhow::Ok(Self::Text(model.content.clone().unwrap_or_default()))
+            }
+            _ => Self::new(&model.url, None),
+        }
+    }
+
+    pub fn kind(&self) -> SubscriptionKind {
+        match self {
+            Self::Remote(_) => SubscriptionKind::Remote,
+            Self::File(_) => SubscriptionKind::File,
+            Self::Text(_) => SubscriptionKind::Text,
+        }
+    }
+
+    /// Load the raw body. Files use their modification time as `Last-Modified`, so an
+    /// unchanged file is reported as not modified just like a `304` response.
+    pub async fn load(&self, options: &FetchOptions) -> anyhow::Result<SubscriptionDownload> {
+        match self {
+            Self::Remote(url) => download_subcriptions(url, options).await,
+            Self::File(path) => {
+                let modified = std::fs::metadata(path)?
+                    .modified()
+                    .ok()
+                    .map(|x| DateTime::<Utc>::from(x).to_rfc3339());
+                let validators = CacheValidators {
+                    etag: None,
+                    last_modified: modified,
+                };
+                let not_modified = validators.last_modified.is_some()
+                    && validators.last_modified == options.validators.last_modified;
+                let text = if not_modified {
+                    String::new()
+                } else {
+                    String::from_utf8_lossy(&std::fs::read(path)?).into_owned()
+                };
+                anyhow::Ok(SubscriptionDownload {
+                    text,
+                    meta: SubscriptionMeta::default(),
+                    validators,
+                    not_modified,
+                })
+            }
+            Self::Text(text) => anyhow::Ok(SubscriptionDownload {
+                text: text.clone(),
+                meta: SubscriptionMeta::default(),
+                validators: CacheValidators::default(),
+                not_modified: false,
+            }),
+        }
+    }
+}
+
+/// Download and parse a subscription, returning the raw download alongside the nodes
+/// so the caller can store it as the first snapshot.
+pub async fn download_subscription_nodes(
+    source: &SubscriptionSource,
+    options: &FetchOptions,
+    filters: &NodeFilters,
+) -> anyhow::Result<(SubscriptionNodes, SubscriptionDownload)> {
+    let download = source.load(options).await?;
+    let mut nodes = parse_subscription_text(&download.text);
+    nodes.apply_filters(filters)?;
+    anyhow::Ok((nodes, download))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose;
+    use reqwest::header::HeaderValue;
+    use crate::apis::test_utils::setup_db;
+
+    #[test]
+    fn test_apply_filters() {
+        let text = [
+            "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#HK%2001",
+            "trojan://b85798ef-e9dc-46a4-9a87-8da4499d36d0@b.example.com:443#JP%2001",
+            "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@c.example.com:443?type=tcp#Expire%3A%202026-12-01",
+            "hy2://secret@d.example.com:443#HK%2002%20hy2",
+        ]
+        .join("\n");
+        let filters = NodeFilters {
+            include: Some("HK|JP".into()),
+            exclude: Some("(?i)expire|traffic".into()),
+            protocols: vec!["vless".into(), "hysteria2".into()],
+            renames: vec![entity::subscribe::RenameRule {
+                pattern: r"^HK (\d+)".into(),
+                replacement: "Hong Kong $1".into(),
+            }],
+        };
+        let mut nodes = parse_subscription_text(&text);
+        nodes.apply_filters(&filters).unwrap();
+        let names: Vec<&str> = nodes.xray.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!["Hong Kong 01"]);
+        assert_eq!(nodes.hysteria[0].name, "Hong Kong 02 hy2");
+
+        let filters = NodeFilters {
+            include: Some("(".into()),
+            ..Default::default()
+        };
+        assert!(parse_subscription_text(&text).apply_filters(&filters).is_err());
+    }
+
+    #[test]
+    fn test_import_report_and_tolerant_decoding() {
+        let mut body = [
+            "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A",
+            "# comment",
+            "hy2://secret@b.example.com:443#B",
+            "ssr://unsupported",
+            "vmess://not-base64-json",
+        ]
+        .join("\n")
+        .into_bytes();
+        body.extend_from_slice(b"\n\xff\xfe\n");
+        // URL-safe alphabet without padding, wrapped like MIME base64
+        let encoded = general_purpose::URL_SAFE_NO_PAD.encode(&body);
+        let wrapped: Vec<String> = encoded
+            .as_bytes()
+            .chunks(40)
+            .map(|x| String::from_utf8(x.to_vec()).unwrap())
+            .collect();
+
+        let nodes = parse_subscription_text(&wrapped.join("\r\n"));
+        assert_eq!(nodes.xray.len(), 1);
+        assert_eq!(nodes.hysteria.len(), 1);
+        let report = &nodes.report;
+        assert_eq!(report.total_lines, 5);
+        assert_eq!(report.parsed.get("vless"), Some(&1));
+        assert_eq!(report.parsed.get("hysteria2"), Some(&1));
+        let skipped: Vec<(usize, &str)> = report
+            .skipped
+            .iter()
+            .map(|x| (x.line, x.reason.as_str()))
+            .collect();
+        assert_eq!(skipped[0], (4, "unsupported protocol: ssr"));
+        assert_eq!(skipped[1].0, 5);
+        assert_eq!(skipped[2], (6, "not a share link"));
+    }
+
+    #[test]
+    fn test_share_links_from_text() {
+        let blob = general_purpose::STANDARD.encode(
+            "trojan://0d385c5b-60c6-4c2c-8a42-313fb67cd60f@c.example.com:443#C\n\
+             hy2://secret@d.example.com:443#D",
+        );
+        let text = format!(
+            "New nodes: vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A and\n\
+             \"hy2://secret@b.example.com:443#B\", see https://example.com/help\n\
+             \n\
+             backup: {blob}\n\
+             vless://broken"
+        );
+        let nodes = SubscriptionNodes::from_text(&text);
+        let names: Vec<&str> = nodes.xray.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["A", "C"]);
+        let names: Vec<&str> = nodes.hysteria.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["B", "D"]);
+        assert_eq!(nodes.report.total_lines, 5);
+        assert_eq!(nodes.report.skipped.len(), 1);
+        assert_eq!(nodes.report.skipped[0].line, 5);
+
+        // A bare base64 subscription body
+        let nodes = SubscriptionNodes::from_text(&blob);
+        assert_eq!((nodes.xray.len(), nodes.hysteria.len()), (1, 1));
+        assert!(SubscriptionNodes::from_text("nothing to see here").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_download_uses_fetch_options() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sub", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            let body = general_purpose::STANDARD
+                .encode("vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A");
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nsubscription-userinfo: upload=1; download=2; total=3\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+
+        let options = FetchOptions {
+            user_agent: Some("clash-verge/v2.0".into()),
+            headers: HashMap::from([("X-Token".into(), "abc".into())]),
+            timeout_secs: Some(5),
+            ..Default::default()
+        };
+        let source = SubscriptionSource::new(&url, None).unwrap();
+        let (nodes, download) =
+            download_subscription_nodes(&source, &options, &NodeFilters::default())
+                .await
+                .unwrap();
+        assert_eq!(nodes.xray.len(), 1);
+        assert_eq!(download.meta.total, Some(3));
+
+        let request = server.join().unwrap();
+        assert!(request.contains("user-agent: clash-verge/v2.0"));
+        assert!(request.contains("x-token: abc"));
+    }
+
+    #[tokio::test]
+    async fn test_file_and_text_sources() {
+        let path = std::env::temp_dir().join(format!("kitty-sub-{}.txt", std::process::id()));
+        let link = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A";
+        std::fs::write(&path, general_purpose::STANDARD.encode(link)).unwrap();
+        let url = reqwest::Url::from_file_path(&path).unwrap().to_string();
+
+        let source = SubscriptionSource::new(&url, None).unwrap();
+        assert_eq!(source.kind(), SubscriptionKind::File);
+        let mut options = FetchOptions::default();
+        let download = source.load(&options).await.unwrap();
+        assert!(!download.not_modified);
+        assert_eq!(parse_subscription_text(&download.text).xray.len(), 1);
+
+        // Unchanged file
+        options.validators = download.validators;
+        assert!(source.load(&options).await.unwrap().not_modified);
+        std::fs::remove_file(&path).unwrap();
+
+        let source = SubscriptionSource::new("", Some(format!("{link}\n{link}"))).unwrap();
+        assert_eq!(source.kind(), SubscriptionKind::Text);
+        let (nodes, _) = download_subscription_nodes(&source, &options, &NodeFilters::default())
+            .await
+            .unwrap();
+        assert_eq!(nodes.xray.len(), 2);
+
+        assert!(SubscriptionSource::new("ftp://example.com/sub", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_manual() {
+        let db = setup_db(&[]).await;
+        let text = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A\n\
+                    not a link\n\
+                    hy2://secret@b.example.com:443#B";
+        let report = SubscriptionNodes::from_text(text)
+            .import_manual(&db)
+            .await
+            .unwrap();
+        assert_eq!(report.changes.added, 2);
+        assert_eq!(report.subscribe_id, None);
+        let stored = xray::Model::fetch_enabled(&db).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].subscribe_id, None);
+        assert_eq!(hysteria::Model::fetch_enabled(&db).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_keeps_node_ids() {
+        let db = setup_db(&["https://example.com/sub"]).await;
+        let first = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A";
+        let second = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@b.example.com:443?type=tcp#B";
+        let third = "hy2://secret@c.example.com:443#C";
+
+        let nodes = parse_subscription_text(&format!("{first}\n{second}"));
+        let changes = nodes.sync(&db, 1).await.unwrap();
+        assert_eq!(changes, SubscriptionChanges { added: 2, updated: 0, removed: 0 });
+        let before = xray::Model::fetch_by_subscribe_id(&db, 1).await.unwrap();
+        let first_id = before.iter().find(|x| x.name == "A").unwrap().id;
+
+        // `A` is renamed, `B` disappears and `C` is new
+        let renamed = first.replace("#A", "#A-renamed");
+        let nodes = parse_subscription_text(&format!("{renamed}\n{third}"));
+        let changes = nodes.sync(&db, 1).await.unwrap();
+        assert_eq!(changes, SubscriptionChanges { added: 1, updated: 1, removed: 1 });
+
+        let after = xray::Model::fetch_by_subscribe_id(&db, 1).await.unwrap();
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].id, first_id);
+        assert_eq!(after[0].name, "A-renamed");
+        assert_eq!(hysteria::Model::fetch_by_subscribe_id(&db, 1).await.unwrap().len(), 1);
+
+        let nodes = parse_subscription_text(&format!("{renamed}\n{third}"));
+        let changes = nodes.sync(&db, 1).await.unwrap();
+        assert_eq!(changes, SubscriptionChanges::default());
+    }
+
+    #[test]
+    fn test_subscription_meta_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "subscription-userinfo",
+            HeaderValue::from_static("upload=455727941; download=6174315083; total=1073741824000; expire=1735689600"),
+        );
+        headers.insert("profile-update-interval", HeaderValue::from_static("24"));
+        let meta = SubscriptionMeta::from_headers(&headers);
+        assert_eq!(meta.upload, Some(455727941));
+        assert_eq!(meta.download, Some(6174315083));
+        assert_eq!(meta.total, Some(1073741824000));
+        assert_eq!(meta.expire_at.unwrap().timestamp(), 1735689600);
+        assert_eq!(meta.update_interval, Some(24));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Subscription-Userinfo",
+            HeaderValue::from_static("upload=0;download=1.5e3;total=;expire=0"),
+        );
+        let meta = SubscriptionMeta::from_headers(&headers);
+        assert_eq!(meta.download, Some(1500));
+        assert_eq!(meta.total, None);
+        assert_eq!(meta.expire_at, None);
+        assert_eq!(meta.update_interval, None);
+    }
 }
@@ -0,0 +1,85 @@
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, ImageFormat, Luma};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Smallest rendered size, large enough to be scanned from a screen by a phone.
+const MIN_DIMENSION: u32 = 320;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrImageFormat {
+    Png,
+    Svg,
+}
+
+impl QrImageFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            QrImageFormat::Png => "image/png",
+            QrImageFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+pub fn render_svg(text: &str) -> Result<String> {
+    let code = QrCode::new(text.as_bytes())?;
+    Ok(code
+        .render::<svg::Color>()
+        .min_dimensions(MIN_DIMENSION, MIN_DIMENSION)
+        .build())
+}
+
+pub fn render_png(text: &str) -> Result<Vec<u8>> {
+    let code = QrCode::new(text.as_bytes())?;
+    let image = code
+        .render::<Luma<u8>>()
+        .min_dimensions(MIN_DIMENSION, MIN_DIMENSION)
+        .build();
+    let mut bytes = Vec::new();
+    DynamicImage::ImageLuma8(image).write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+    Ok(bytes)
+}
+
+/// Decode every QR code found in an image file, e.g. a screenshot.
+pub fn decode_image_file(path: &Path) -> Result<Vec<String>> {
+    let image = image::open(path)
+        .map_err(|e| anyhow!("Failed to open image {}: {}", path.display(), e))?
+        .to_luma8();
+    let (width, height) = image.dimensions();
+    let mut prepared =
+        rqrr::PreparedImage::prepare_from_greyscale(width as usize, height as usize, |x, y| {
+            image.get_pixel(x as u32, y as u32).0[0]
+        });
+    let mut contents = Vec::new();
+    for grid in prepared.detect_grids() {
+        match grid.decode() {
+            Ok((_, content)) => contents.push(content),
+            Err(e) => log::warn!("Failed to decode QR code in {}: {}", path.display(), e),
+        }
+    }
+    if contents.is_empty() {
+        return Err(anyhow!("No QR code found in {}", path.display()));
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() {
+        let link = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?encryption=none&type=tcp#HK%2001";
+        let path = std::env::temp_dir().join(format!("kitty-qr-{}.png", std::process::id()));
+        std::fs::write(&path, render_png(link).unwrap()).unwrap();
+        let contents = decode_image_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents, vec![link.to_string()]);
+
+        assert!(render_svg(link).unwrap().starts_with("<?xml"));
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use entity::{hysteria, xray};
+use sea_orm::ConnectionTrait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+use super::parse_subscription::SubscriptionNodes;
+
+/// Nodes picked for export: explicit IDs and / or every node of a subscription.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct NodeSelection {
+    #[serde(default)]
+    pub xray_ids: Vec<i32>,
+    #[serde(default)]
+    pub hysteria_ids: Vec<i32>,
+    #[serde(default)]
+    pub subscribe_id: Option<i32>,
+}
+
+impl NodeSelection {
+    /// A single node, `proxy_type` being `xray` or `hysteria` as in `Proxy`.
+    pub fn single(proxy_type: &str, id: i32) -> Result<Self> {
+        match proxy_type {
+            "xray" => Ok(Self {
+                xray_ids: vec![id],
+                ..Default::default()
+            }),
+            "hysteria" => Ok(Self {
+                hysteria_ids: vec![id],
+                ..Default::default()
+            }),
+            _ => Err(anyhow!("Unknown proxy type: {}", proxy_type)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xray_ids.is_empty() && self.hysteria_ids.is_empty() && self.subscribe_id.is_none()
+    }
+
+    /// Load the selected nodes, each at most once, ordered by ID.
+    pub async fn load<C>(&self, db: &C) -> Result<SubscriptionNodes>
+    where
+        C: ConnectionTrait,
+    {
+        if self.is_empty() {
+            return Err(anyhow!("No nodes selected"));
+        }
+        let mut nodes = SubscriptionNodes::default();
+        if !self.xray_ids.is_empty() {
+            nodes.xray = xray::Model::fetch_by_ids(db, self.xray_ids.clone()).await?;
+        }
+        if !self.hysteria_ids.is_empty() {
+            nodes.hysteria = hysteria::Model::fetch_by_ids(db, self.hysteria_ids.clone()).await?;
+        }
+        if let Some(subscribe_id) = self.subscribe_id {
+            nodes
+                .xray
+                .extend(xray::Model::fetch_by_subscribe_id(db, subscribe_id).await?);
+            nodes
+                .hysteria
+                .extend(hysteria::Model::fetch_by_subscribe_id(db, subscribe_id).await?);
+        }
+        nodes.xray.sort_by_key(|x| x.id);
+        nodes.xray.dedup_by_key(|x| x.id);
+        nodes.hysteria.sort_by_key(|x| x.id);
+        nodes.hysteria.dedup_by_key(|x| x.id);
+        Ok(nodes)
+    }
+}
+
+/// Nodes added manually or belonging to an enabled subscription.
+pub async fn enabled_nodes<C>(db: &C) -> Result<SubscriptionNodes>
+where
+    C: ConnectionTrait,
+{
+    Ok(SubscriptionNodes {
+        xray: xray::Model::fetch_enabled(db).await?,
+        hysteria: hysteria::Model::fetch_enabled(db).await?,
+        ..Default::default()
+    })
+}
+
+/// Share links of the nodes, xray nodes first.
+pub fn share_links(nodes: &SubscriptionNodes) -> Vec<String> {
+    let xray = nodes.xray.iter().map(|x| x.to_share_link());
+    let hysteria = nodes.hysteria.iter().map(|x| x.to_share_link());
+    xray.chain(hysteria).collect()
+}
+
+/// Base64 subscription body understood by `parse_subscription_text`.
+pub fn encode_subscription(links: &[String]) -> String {
+    general_purpose::STANDARD.encode(links.join("\n"))
+}
+
+/// Node names made unique by numbering repeats, as Clash and sing-box refer to
+/// proxies by name. Xray nodes come first, like in `share_links`.
+pub fn unique_names(nodes: &SubscriptionNodes) -> Vec<String> {
+    let names = nodes.xray.iter().map(|x| x.name.as_str());
+    let names = names.chain(nodes.hysteria.iter().map(|x| x.name.as_str()));
+    let mut seen = HashSet::new();
+    names
+        .map(|name| {
+            let mut unique = name.to_string();
+            let mut index = 1;
+            while !seen.insert(unique.clone()) {
+                index += 1;
+                unique = format!("{name} ({index})");
+            }
+            unique
+        })
+        .collect()
+}
+
+/// Host without the brackets of an IPv6 literal.
+pub fn bare_host(address: &str) -> &str {
+    address.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// Drop `null` members from JSON objects so optional fields are left out of exports.
+pub fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, x| !x.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::parse_subscription::parse_subscription_text;
+
+    #[test]
+    fn test_encode_subscription_round_trip() {
+        let text = [
+            "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=ws&security=tls&sni=a.example.com&path=%2Fws#HK%2001",
+            "trojan://0d385c5b-60c6-4c2c-8a42-313fb67cd60f@b.example.com:443?sni=b.example.com#JP",
+            "trojan://secret@d.example.com:443?sni=d.example.com#Password",
+            "hy2://secret@c.example.com:443?obfs=salamander&obfs-password=pw#HK%2002%20hy2",
+        ]
+        .join("\n");
+        let nodes = parse_subscription_text(&text);
+        let links = share_links(&nodes);
+        assert_eq!(links.len(), 4);
+
+        let exported = parse_subscription_text(&encode_subscription(&links));
+        assert_eq!(exported.xray, nodes.xray);
+        assert_eq!(exported.hysteria, nodes.hysteria);
+        assert!(exported.report.skipped.is_empty());
+    }
+}
@@ -0,0 +1,662 @@
+use std::collections::HashMap;
+
+use entity::hysteria::{self, Bandwidth, Obfs, PortHopping, Tls};
+use entity::xray::{self, Protocol, ShadowsocksSettings, StreamSettings, TuicSettings};
+use sea_orm::ActiveEnum;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::parse_subscription::SubscriptionNodes;
+use super::share_links::{bare_host, strip_nulls, unique_names};
+
+/// Outbound types that describe a proxy server; the rest (selector, urltest, direct,
+/// block, dns, ...) are routing helpers.
+const SINGBOX_PROXY_TYPES: [&str; 10] = [
+    "vless",
+    "vmess",
+    "trojan",
+    "shadowsocks",
+    "hysteria2",
+    "hysteria",
+    "tuic",
+    "wireguard",
+    "socks",
+    "http",
+];
+
+#[derive(Debug, Deserialize)]
+struct SingBoxConfig {
+    outbounds: Vec<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SingBoxUtls {
+    enabled: bool,
+    fingerprint: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SingBoxReality {
+    enabled: bool,
+    public_key: Option<String>,
+    short_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SingBoxTls {
+    enabled: bool,
+    server_name: Option<String>,
+    insecure: bool,
+    alpn: Vec<String>,
+    certificate_path: Option<String>,
+    utls: Option<SingBoxUtls>,
+    reality: Option<SingBoxReality>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SingBoxTransport {
+    #[serde(rename = "type")]
+    r#type: String,
+    path: Option<String>,
+    headers: HashMap<String, Value>,
+    /// A list for `http`, a string for `httpupgrade`
+    host: Option<Value>,
+    service_name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SingBoxObfs {
+    #[serde(rename = "type")]
+    r#type: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SingBoxOutbound {
+    #[serde(rename = "type")]
+    r#type: String,
+    #[serde(default)]
+    tag: String,
+    server: String,
+    server_port: u16,
+    uuid: Option<String>,
+    flow: Option<String>,
+    password: Option<String>,
+    method: Option<String>,
+    plugin: Option<String>,
+    plugin_opts: Option<String>,
+    tls: Option<SingBoxTls>,
+    transport: Option<SingBoxTransport>,
+    up_mbps: Option<u64>,
+    down_mbps: Option<u64>,
+    obfs: Option<SingBoxObfs>,
+    /// Hysteria2 hopping ranges, e.g. `20000:30000`
+    #[serde(default)]
+    server_ports: Vec<String>,
+    /// Duration like `30s`
+    hop_interval: Option<String>,
+    congestion_control: Option<String>,
+    udp_relay_mode: Option<String>,
+}
+
+impl SingBoxOutbound {
+    fn name(&self) -> String {
+        if self.tag.is_empty() {
+            format!("{}:{}", self.server, self.server_port)
+        } else {
+            self.tag.clone()
+        }
+    }
+
+    /// Map the sing-box outbound onto the share-link parameters understood by
+    /// `StreamSettings::from_query_params`.
+    fn query_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        let transport = self.transport.as_ref();
+        params.insert(
+            "type".to_string(),
+            transport
+                .map(|x| x.r#type.clone())
+                .filter(|x| !x.is_empty())
+                .unwrap_or_else(|| "tcp".into()),
+        );
+        if let Some(transport) = transport {
+            if let Some(path) = &transport.path {
+                params.insert("path".into(), path.clone());
+            }
+            let host = match &transport.host {
+                Some(Value::String(host)) => Some(host.clone()),
+                Some(Value::Array(hosts)) => Some(
+                    hosts
+                        .iter()
+                        .filter_map(|x| x.as_str())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+                _ => transport
+                    .headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("host"))
+                    .and_then(|(_, value)| match value {
+                        Value::String(host) => Some(host.clone()),
+                        Value::Array(hosts) => {
+                            hosts.first().and_then(|x| x.as_str()).map(Into::into)
+                        }
+                        _ => None,
+                    }),
+            }
+            .filter(|x| !x.is_empty());
+            if let Some(host) = host {
+                params.insert("host".into(), host);
+            }
+            if let Some(service_name) = &transport.service_name {
+                params.insert("serviceName".into(), service_name.clone());
+            }
+        }
+        if let Some(tls) = self.tls.as_ref().filter(|x| x.enabled) {
+            match tls.reality.as_ref().filter(|x| x.enabled) {
+                Some(reality) => {
+                    params.insert("security".into(), "reality".into());
+                    if let Some(public_key) = &reality.public_key {
+                        params.insert("pbk".into(), public_key.clone());
+                    }
+                    if let Some(short_id) = &reality.short_id {
+                        params.insert("sid".into(), short_id.clone());
+                    }
+                }
+                None => {
+                    params.insert("security".into(), "tls".into());
+                }
+            }
+            if let Some(server_name) = &tls.server_name {
+                params.insert("sni".into(), server_name.clone());
+            }
+            if tls.insecure {
+                params.insert("allowInsecure".into(), "1".into());
+            }
+            if !tls.alpn.is_empty() {
+                params.insert("alpn".into(), tls.alpn.join(","));
+            }
+            if let Some(fingerprint) = tls
+                .utls
+                .as_ref()
+                .filter(|x| x.enabled)
+                .and_then(|x| x.fingerprint.as_ref())
+            {
+                params.insert("fp".into(), fingerprint.clone());
+            }
+        }
+        params
+    }
+
+    fn to_xray(&self, protocol: Protocol) -> anyhow::Result<xray::Model> {
+        let uuid = match protocol {
+            Protocol::Trojan | Protocol::Shadowsocks => self.password.clone(),
+            _ => self.uuid.clone(),
+        }
+        .ok_or_else(|| anyhow::anyhow!("missing credential for {}", self.name()))?;
+        let stream_settings = StreamSettings::from_query_params(&self.query_params())?;
+        let mut model = xray::Model::new(
+            self.name(),
+            protocol,
+            uuid,
+            self.server.clone(),
+            self.server_port,
+            stream_settings,
+        );
+        model.flow = self.flow.clone().filter(|x| !x.is_empty());
+        Ok(model)
+    }
+
+    fn to_shadowsocks(&self) -> anyhow::Result<xray::Model> {
+        let method = self
+            .method
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("missing method for {}", self.name()))?;
+        let settings =
+            ShadowsocksSettings::new(method, self.plugin.clone(), self.plugin_opts.clone())?;
+        let mut model = self.to_xray(Protocol::Shadowsocks)?;
+        model.shadowsocks = Some(settings);
+        Ok(model)
+    }
+
+    fn to_tuic(&self) -> anyhow::Result<xray::Model> {
+        let password = self
+            .password
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("missing password for {}", self.name()))?;
+        let settings = TuicSettings::new(
+            password,
+            self.congestion_control.clone(),
+            self.udp_relay_mode.clone(),
+        )?;
+        let mut model = self.to_xray(Protocol::Tuic)?;
+        model.tuic = Some(settings);
+        Ok(model)
+    }
+
+    fn to_hysteria(&self) -> anyhow::Result<hysteria::Model> {
+        let auth = self
+            .password
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("missing password for {}", self.name()))?;
+        let tls = self.tls.as_ref();
+        let obfs = self
+            .obfs
+            .as_ref()
+            .filter(|x| !x.r#type.is_empty() && x.r#type != "none")
+            .map(|x| Obfs {
+                r#type: x.r#type.clone(),
+                password: x.password.clone(),
+            });
+        let ports = self.server_ports.join(",").replace(':', "-");
+        let port_hopping = PortHopping::from_ports(&ports)?.map(|mut hopping| {
+            hopping.interval = self
+                .hop_interval
+                .as_ref()
+                .and_then(|x| x.trim_end_matches('s').parse().ok());
+            hopping
+        });
+        Ok(hysteria::Model {
+            id: Default::default(),
+            name: self.name(),
+            server: format!("{}:{}", self.server, self.server_port),
+            auth,
+            tls: Tls {
+                sni: tls
+                    .and_then(|x| x.server_name.clone())
+                    .unwrap_or_else(|| self.server.clone()),
+                insecure: tls.map(|x| x.insecure).unwrap_or_default(),
+                pin_sha256: None,
+                ca: tls.and_then(|x| x.certificate_path.clone()),
+            },
+            bandwidth: Bandwidth {
+                up: self.up_mbps.map(|x| format!("{x} mbps")).unwrap_or_default(),
+                down: self.down_mbps.map(|x| format!("{x} mbps")).unwrap_or_default(),
+            },
+            obfs,
+            subscribe_id: None,
+            port_hopping,
+        })
+    }
+}
+
+/// Parse the `outbounds` of a sing-box config. Returns `None` if the text is not
+/// a sing-box config; unsupported outbounds (selector, direct, ...) are skipped.
+pub fn parse_singbox_config(text: &str) -> Option<SubscriptionNodes> {
+    let config: SingBoxConfig = serde_json::from_str(text).ok()?;
+    let mut nodes = SubscriptionNodes::default();
+    for (index, value) in config.outbounds.into_iter().enumerate() {
+        let r#type = value.get("type").and_then(|x| x.as_str()).unwrap_or_default();
+        // Selectors, direct/block outbounds etc. aren't proxies
+        if !SINGBOX_PROXY_TYPES.contains(&r#type) {
+            continue;
+        }
+        nodes.report.total_lines += 1;
+        let tag = value
+            .get("tag")
+            .and_then(|x| x.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let outbound = match serde_json::from_value::<SingBoxOutbound>(value) {
+            Ok(outbound) => outbound,
+            Err(e) => {
+                nodes.report.skip(index + 1, &tag, e);
+                continue;
+            }
+        };
+        let result = match outbound.r#type.as_str() {
+            "vless" | "vmess" | "trojan" => outbound
+                .r#type
+                .parse::<Protocol>()
+                .and_then(|protocol| outbound.to_xray(protocol))
+                .map(|model| nodes.push_xray(model)),
+            "shadowsocks" => outbound.to_shadowsocks().map(|model| nodes.push_xray(model)),
+            "tuic" => outbound.to_tuic().map(|model| nodes.push_xray(model)),
+            "hysteria2" => outbound.to_hysteria().map(|model| nodes.push_hysteria(model)),
+            other => Err(anyhow::anyhow!("unsupported outbound type: {other}")),
+        };
+        if let Err(e) = result {
+            nodes.report.skip(index + 1, &tag, e);
+        }
+    }
+    Some(nodes)
+}
+
+/// sing-box outbound of an xray node, `None` if its transport has no sing-box
+/// equivalent.
+fn xray_to_singbox(model: &xray::Model, tag: &str) -> Option<Value> {
+    let params: HashMap<&str, String> = model
+        .stream_settings()
+        .to_query_params()
+        .into_iter()
+        .collect();
+    let param = |key: &str| params.get(key).cloned();
+    if param("type").as_deref() == Some("xhttp") {
+        log::warn!("Skipping {} in the sing-box export: XHTTP is not supported", tag);
+        return None;
+    }
+    let mut outbound = json!({
+        "type": model.protocol.to_value(),
+        "tag": tag,
+        "server": bare_host(&model.address),
+        "server_port": model.port,
+    });
+    match model.protocol {
+        Protocol::Vless => {
+            outbound["uuid"] = json!(model.uuid);
+            outbound["flow"] = json!(model.flow);
+        }
+        Protocol::Vmess => {
+            outbound["uuid"] = json!(model.uuid);
+            outbound["security"] = json!("auto");
+            outbound["alter_id"] = json!(0);
+        }
+        Protocol::Trojan => outbound["password"] = json!(model.uuid),
+        Protocol::Shadowsocks => {
+            let settings = model.shadowsocks.as_ref();
+            outbound["password"] = json!(model.uuid);
+            outbound["method"] = json!(settings.map(|x| x.method.clone()));
+            outbound["plugin"] = json!(settings.and_then(|x| x.plugin.clone()));
+            outbound["plugin_opts"] = json!(settings.and_then(|x| x.plugin_opts.clone()));
+        }
+        Protocol::Tuic => {
+            let settings = model.tuic.as_ref();
+            outbound["uuid"] = json!(model.uuid);
+            outbound["password"] = json!(settings.map(|x| x.password.clone()));
+            outbound["congestion_control"] = json!(settings.map(|x| x.congestion_control.clone()));
+            outbound["udp_relay_mode"] = json!(settings.map(|x| x.udp_relay_mode.clone()));
+        }
+    }
+    if let Some(security) = param("security") {
+        outbound["tls"] = json!({
+            "enabled": true,
+            "server_name": param("sni"),
+            "insecure": params.contains_key("allowInsecure"),
+            "alpn": param("alpn").map(|x| x.split(',').map(String::from).collect::<Vec<_>>()),
+            "utls": param("fp").map(|fp| json!({ "enabled": true, "fingerprint": fp })),
+            "reality": (security == "reality").then(|| json!({
+                "enabled": true,
+                "public_key": param("pbk"),
+                "short_id": param("sid"),
+            })),
+        });
+    }
+    match param("type").as_deref() {
+        Some("ws") => {
+            outbound["transport"] = json!({
+                "type": "ws",
+                "path": param("path"),
+                "headers": param("host").map(|host| json!({ "Host": host })),
+            });
+        }
+        Some("http") => {
+            outbound["transport"] = json!({
+                "type": "http",
+                "host": param("host").map(|x| x.split(',').map(String::from).collect::<Vec<_>>()),
+                "path": param("path"),
+            });
+        }
+        Some("httpupgrade") => {
+            outbound["transport"] = json!({
+                "type": "httpupgrade",
+                "host": param("host"),
+                "path": param("path"),
+            });
+        }
+        Some("grpc") => {
+            outbound["transport"] = json!({
+                "type": "grpc",
+                "service_name": param("serviceName"),
+            });
+        }
+        _ => {}
+    }
+    Some(outbound)
+}
+
+/// Plain mbps value of a bandwidth like `100 mbps`; sing-box has no other units.
+fn bandwidth_mbps(value: &str) -> Option<u64> {
+    let mut parts = value.split_whitespace();
+    let mbps = parts.next()?.parse().ok()?;
+    match parts.next() {
+        None => Some(mbps),
+        Some(unit) if unit.eq_ignore_ascii_case("mbps") => Some(mbps),
+        Some(_) => None,
+    }
+}
+
+/// sing-box outbound of a Hysteria2 node.
+fn hysteria_to_singbox(model: &hysteria::Model, tag: &str) -> Value {
+    let (server, port) = model.host_port();
+    json!({
+        "type": "hysteria2",
+        "tag": tag,
+        "server": server,
+        "server_port": port,
+        "password": model.auth,
+        "up_mbps": bandwidth_mbps(&model.bandwidth.up),
+        "down_mbps": bandwidth_mbps(&model.bandwidth.down),
+        "obfs": model.obfs.as_ref().map(|x| json!({ "type": x.r#type, "password": x.password })),
+        "server_ports": model.port_hopping.as_ref().map(|x| {
+            x.ports.split(',').map(|range| range.replace('-', ":")).collect::<Vec<_>>()
+        }),
+        "hop_interval": model.port_hopping.as_ref().and_then(|x| x.interval).map(|x| format!("{x}s")),
+        "tls": {
+            "enabled": true,
+            "server_name": model.tls.sni,
+            "insecure": model.tls.insecure,
+            "certificate_path": model.tls.ca,
+        },
+    })
+}
+
+/// Render nodes as a sing-box config whose `proxy` selector picks between them.
+pub fn export_singbox_config(nodes: &SubscriptionNodes) -> anyhow::Result<String> {
+    let all_tags = unique_names(nodes);
+    let (mut proxies, mut tags): (Vec<Value>, Vec<String>) = nodes
+        .xray
+        .iter()
+        .zip(&all_tags)
+        .filter_map(|(model, tag)| xray_to_singbox(model, tag).map(|x| (x, tag.clone())))
+        .unzip();
+    for (model, tag) in nodes.hysteria.iter().zip(&all_tags[nodes.xray.len()..]) {
+        proxies.push(hysteria_to_singbox(model, tag));
+        tags.push(tag.clone());
+    }
+    let mut outbounds = vec![json!({
+        "type": "selector",
+        "tag": "proxy",
+        "outbounds": tags,
+    })];
+    outbounds.extend(proxies);
+    outbounds.push(json!({ "type": "direct", "tag": "direct" }));
+    let mut config = json!({
+        "outbounds": outbounds,
+        "route": { "final": "proxy" },
+    });
+    strip_nulls(&mut config);
+    Ok(serde_json::to_string_pretty(&config)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINGBOX_CONFIG: &str = r#"{
+        "log": {"level": "info"},
+        "outbounds": [
+            {"type": "selector", "tag": "proxy", "outbounds": ["vless-reality"]},
+            {
+                "type": "vless",
+                "tag": "vless-reality",
+                "server": "example.com",
+                "server_port": 443,
+                "uuid": "0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a",
+                "flow": "xtls-rprx-vision",
+                "tls": {
+                    "enabled": true,
+                    "server_name": "www.microsoft.com",
+                    "utls": {"enabled": true, "fingerprint": "chrome"},
+                    "reality": {"enabled": true, "public_key": "pubkey", "short_id": "abcd"}
+                }
+            },
+            {
+                "type": "vmess",
+                "tag": "vmess-ws",
+                "server": "1.2.3.4",
+                "server_port": 8080,
+                "uuid": "0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a",
+                "transport": {"type": "ws", "path": "/ws", "headers": {"Host": "cdn.example.com"}}
+            },
+            {
+                "type": "trojan",
+                "tag": "trojan-grpc",
+                "server": "trojan.example.com",
+                "server_port": 443,
+                "password": "secret",
+                "tls": {"enabled": true, "server_name": "trojan.example.com", "alpn": ["h2", "http/1.1"]},
+                "transport": {"type": "grpc", "service_name": "grpc-svc"}
+            },
+            {
+                "type": "hysteria2",
+                "tag": "hy2",
+                "server": "hy2.example.com",
+                "server_port": 8443,
+                "password": "hy2pass",
+                "up_mbps": 50,
+                "down_mbps": 100,
+                "obfs": {"type": "salamander", "password": "obfspass"},
+                "server_ports": ["20000:30000", "40000:40100"],
+                "hop_interval": "30s",
+                "tls": {"enabled": true, "server_name": "hy2.example.com", "insecure": true}
+            },
+            {
+                "type": "shadowsocks",
+                "tag": "ss",
+                "server": "ss.example.com",
+                "server_port": 8388,
+                "method": "2022-blake3-aes-128-gcm",
+                "password": "YctPZ6U7xPPcU+gp3u+0tx/tRizJN9K8y+uKlW2qjlI="
+            },
+            {
+                "type": "tuic",
+                "tag": "tuic",
+                "server": "tuic.example.com",
+                "server_port": 8443,
+                "uuid": "0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a",
+                "password": "tuicpass",
+                "congestion_control": "new_reno",
+                "tls": {"enabled": true, "server_name": "tuic.example.com", "alpn": ["h3"]}
+            },
+            {
+                "type": "vless",
+                "tag": "vless-h2",
+                "server": "h2.example.com",
+                "server_port": 443,
+                "uuid": "0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a",
+                "tls": {"enabled": true, "server_name": "h2.example.com"},
+                "transport": {"type": "http", "host": ["a.example.com", "b.example.com"], "path": "/h2"}
+            },
+            {
+                "type": "vless",
+                "tag": "vless-httpupgrade",
+                "server": "up.example.com",
+                "server_port": 443,
+                "uuid": "0c4a5fdc-4ec4-4d5f-8fa4-0a3e4d7c9c1a",
+                "tls": {"enabled": true, "server_name": "up.example.com"},
+                "transport": {"type": "httpupgrade", "host": "cdn.example.com", "path": "/up"}
+            },
+            {"type": "direct", "tag": "direct"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_singbox_config() {
+        let nodes = parse_singbox_config(SINGBOX_CONFIG).unwrap();
+        assert_eq!(nodes.xray.len(), 7);
+        assert_eq!(nodes.hysteria.len(), 1);
+
+        let vless = serde_json::to_value(&nodes.xray[0]).unwrap();
+        assert_eq!(vless["name"], "vless-reality");
+        assert_eq!(vless["stream_settings"]["security"], "reality");
+        assert_eq!(vless["stream_settings"]["realitySettings"]["publicKey"], "pubkey");
+        assert_eq!(vless["flow"], "xtls-rprx-vision");
+
+        let vmess = serde_json::to_value(&nodes.xray[1]).unwrap();
+        assert_eq!(vmess["stream_settings"]["network"], "ws");
+        assert_eq!(vmess["stream_settings"]["wsSettings"]["path"], "/ws");
+
+        let trojan = serde_json::to_value(&nodes.xray[2]).unwrap();
+        assert_eq!(trojan["uuid"], "secret");
+        assert_eq!(trojan["stream_settings"]["network"], "grpc");
+        assert_eq!(trojan["stream_settings"]["tlsSettings"]["alpn"][0], "h2");
+
+        let hy2 = &nodes.hysteria[0];
+        assert_eq!(hy2.server, "hy2.example.com:8443");
+        assert_eq!(hy2.bandwidth.down, "100 mbps");
+        assert!(hy2.tls.insecure);
+        assert_eq!(hy2.obfs.as_ref().unwrap().r#type, "salamander");
+        let hopping = hy2.port_hopping.as_ref().unwrap();
+        assert_eq!(hopping.ports, "20000-30000,40000-40100");
+        assert_eq!(hopping.interval, Some(30));
+
+        let ss = &nodes.xray[3];
+        assert_eq!(ss.protocol, Protocol::Shadowsocks);
+        assert_eq!(ss.shadowsocks.as_ref().unwrap().method, "2022-blake3-aes-128-gcm");
+
+        let tuic = &nodes.xray[4];
+        assert_eq!(tuic.protocol, Protocol::Tuic);
+        let settings = tuic.tuic.as_ref().unwrap();
+        assert_eq!(settings.password, "tuicpass");
+        assert_eq!(settings.congestion_control, "new_reno");
+        assert_eq!(settings.udp_relay_mode, "native");
+
+        let h2 = nodes.xray[5].stream_settings();
+        assert_eq!(h2.network(), "http");
+        assert_eq!(h2.http_path(), Some("/h2"));
+        let upgrade = nodes.xray[6].stream_settings();
+        assert_eq!(upgrade.network(), "httpupgrade");
+        assert_eq!(upgrade.http_host(), Some("cdn.example.com"));
+    }
+
+    #[test]
+    fn test_export_singbox_config_round_trip() {
+        let nodes = parse_singbox_config(SINGBOX_CONFIG).unwrap();
+        let exported = export_singbox_config(&nodes).unwrap();
+        let parsed = parse_singbox_config(&exported).unwrap();
+        assert!(parsed.report.skipped.is_empty());
+        assert_eq!(parsed.xray, nodes.xray);
+        assert_eq!(parsed.hysteria, nodes.hysteria);
+
+        let config: Value = serde_json::from_str(&exported).unwrap();
+        assert_eq!(config["outbounds"][0]["outbounds"][7], "hy2");
+        assert_eq!(bandwidth_mbps("1 gbps"), None);
+    }
+
+    #[test]
+    fn test_export_skips_xhttp() {
+        use crate::apis::parse_subscription::parse_subscription_text;
+
+        let nodes = parse_subscription_text(
+            "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=xhttp&security=tls&sni=a.example.com&path=%2Fx#X\n\
+             vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@b.example.com:443?type=tcp#T",
+        );
+        let exported = export_singbox_config(&nodes).unwrap();
+        let config: Value = serde_json::from_str(&exported).unwrap();
+        assert_eq!(config["outbounds"][0]["outbounds"], json!(["T"]));
+        assert_eq!(config["outbounds"][1]["tag"], "T");
+        assert_eq!(config["outbounds"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_non_singbox_body() {
+        assert!(parse_singbox_config("proxies: []").is_none());
+        assert!(parse_singbox_config("{\"inbounds\": []}").is_none());
+    }
+}
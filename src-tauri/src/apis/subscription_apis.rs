@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use entity::{subscribe, subscription_snapshot};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, NotSet, Set, TransactionTrait};
+
+use super::parse_subscription::{
+    parse_subscription_text, CacheValidators, FetchOptions, ImportReport, SubscriptionDownload,
+    SubscriptionSource,
+};
+
+/// Number of raw snapshots kept per subscription.
+const MAX_SNAPSHOTS: usize = 10;
+
+/// Per-subscription locks, so a background refresh and a manual one never sync
+/// the nodes of the same subscription at the same time.
+static SYNC_LOCKS: LazyLock<Mutex<HashMap<i32, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Default::default);
+
+fn sync_lock(subscribe_id: i32) -> Arc<tokio::sync::Mutex<()>> {
+    let mut locks = SYNC_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+    locks.entry(subscribe_id).or_default().clone()
+}
+
+pub struct SubscriptionAPI;
+
+impl SubscriptionAPI {
+    /// Download a subscription and reconcile its nodes, updating its sync metadata.
+    ///
+    /// The request is conditional on the validators of the latest snapshot; when the
+    /// provider answers `304 Not Modified` only `last_sync_at` is touched.
+    pub async fn refresh<C>(db: &C, sub: subscribe::Model) -> Result<ImportReport>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let lock = sync_lock(sub.id);
+        let _guard = lock.lock().await;
+
+        // Download and parse BEFORE transaction
+        let mut options = FetchOptions::for_subscription(db, &sub).await?;
+        if let Some(snapshot) = subscription_snapshot::Model::latest(db, sub.id).await? {
+            options.validators = CacheValidators::from_snapshot(&snapshot);
+        }
+        let download = SubscriptionSource::from_model(&sub)?.load(&options).await?;
+
+        if download.not_modified {
+            let id = sub.id;
+            let mut sub_model: subscribe::ActiveModel = sub.into();
+            sub_model.last_sync_at = Set(Some(Utc::now()));
+            sub_model.update(db).await?;
+            return Ok(ImportReport {
+                subscribe_id: Some(id),
+                not_modified: true,
+                ..Default::default()
+            });
+        }
+
+        let mut nodes = parse_subscription_text(&download.text);
+        // An empty or broken body would wipe every node; keep them and the
+        // previous snapshot instead. Nodes dropped by the filters are fine.
+        if nodes.is_empty() {
+            return Err(anyhow!("No valid proxies found in subscription"));
+        }
+        nodes.apply_filters(&sub.filters.clone().unwrap_or_default())?;
+        nodes.skip_duplicates(db, Some(sub.id)).await?;
+        let mut report = nodes.take_report();
+        report.subscribe_id = Some(sub.id);
+
+        let txn = db.begin().await?;
+
+        // Reconcile nodes, keeping the IDs of unchanged ones
+        report.changes = nodes.sync(&txn, sub.id).await?;
+        Self::save_snapshot(&txn, sub.id, &download).await?;
+
+        // Update subscription timestamp
+        let mut sub_model: subscribe::ActiveModel = sub.into();
+        sub_model.updated_at = Set(Utc::now());
+        sub_model.last_sync_at = Set(Some(Utc::now()));
+        download.meta.apply(&mut sub_model);
+        sub_model.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(report)
+    }
+
+    /// Store a download as the latest snapshot of a subscription, keeping at most
+    /// `MAX_SNAPSHOTS` of them.
+    pub async fn save_snapshot<C>(
+        db: &C,
+        subscribe_id: i32,
+        download: &SubscriptionDownload,
+    ) -> Result<()>
+    where
+        C: ConnectionTrait,
+    {
+        subscription_snapshot::ActiveModel {
+            id: NotSet,
+            subscribe_id: Set(subscribe_id),
+            etag: Set(download.validators.etag.clone()),
+            last_modified: Set(download.validators.last_modified.clone()),
+            content: Set(download.text.clone()),
+            created_at: Set(Utc::now()),
+        }
+        .insert(db)
+        .await?;
+        subscription_snapshot::Model::prune(db, subscribe_id, MAX_SNAPSHOTS).await?;
+        Ok(())
+    }
+
+    /// Restore the nodes of a subscription from one of its earlier snapshots.
+    pub async fn rollback<C>(
+        db: &C,
+        sub: subscribe::Model,
+        snapshot_id: i32,
+    ) -> Result<ImportReport>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let snapshot = subscription_snapshot::Model::get_by_id(db, snapshot_id)
+            .await?
+            .filter(|x| x.subscribe_id == sub.id)
+            .ok_or_else(|| anyhow!("Snapshot not found"))?;
+        Self::sync_snapshot(db, sub, &snapshot, true).await
+    }
+
+    /// Re-apply the subscription's filters to its latest snapshot, e.g. after the
+    /// filters were edited. A conditional refresh would otherwise keep the old nodes
+    /// until the provider changes the list.
+    pub async fn reapply_filters<C>(db: &C, sub: subscribe::Model) -> Result<ImportReport>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        match subscription_snapshot::Model::latest(db, sub.id).await? {
+            Some(snapshot) => Self::sync_snapshot(db, sub, &snapshot, false).await,
+            None => Ok(ImportReport {
+                subscribe_id: Some(sub.id),
+                ..Default::default()
+            }),
+        }
+    }
+
+    async fn sync_snapshot<C>(
+        db: &C,
+        sub: subscribe::Model,
+        snapshot: &subscription_snapshot::Model,
+        require_nodes: bool,
+    ) -> Result<ImportReport>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let lock = sync_lock(sub.id);
+        let _guard = lock.lock().await;
+
+        let mut nodes = parse_subscription_text(&snapshot.content);
+        nodes.apply_filters(&sub.filters.clone().unwrap_or_default())?;
+        if require_nodes && nodes.is_empty() {
+            return Err(anyhow!("No valid proxies found in snapshot"));
+        }
+        nodes.skip_duplicates(db, Some(sub.id)).await?;
+
+        let mut report = nodes.take_report();
+        report.subscribe_id = Some(sub.id);
+
+        let txn = db.begin().await?;
+        report.changes = nodes.sync(&txn, sub.id).await?;
+        let mut sub_model: subscribe::ActiveModel = sub.into();
+        sub_model.updated_at = Set(Utc::now());
+        sub_model.update(&txn).await?;
+        txn.commit().await?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::parse_subscription::SubscriptionChanges;
+    use crate::apis::test_utils::setup_db;
+    use entity::xray;
+    use std::io::{Read, Write};
+
+    /// Serve one response per body; `None` answers `304` if the request is conditional.
+    fn serve(
+        listener: std::net::TcpListener,
+        bodies: Vec<Option<&'static str>>,
+    ) -> std::thread::JoinHandle<Vec<String>> {
+        std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (index, body) in bodies.into_iter().enumerate() {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let response = match body {
+                    Some(body) => format!(
+                        "HTTP/1.1 200 OK\r\netag: \"v{}\"\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        index,
+                        body.len(),
+                        body
+                    ),
+                    None if request.contains("if-none-match") => {
+                        "HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n".to_string()
+                    }
+                    None => panic!("expected a conditional request"),
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(request);
+            }
+            requests
+        })
+    }
+
+    #[tokio::test]
+    async fn test_conditional_refresh_and_rollback() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sub", listener.local_addr().unwrap());
+        let good = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A\n\
+                    vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@b.example.com:443?type=tcp#B";
+        let broken = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A";
+        let server = serve(listener, vec![Some(good), None, Some(broken)]);
+        let db = setup_db(&[&url]).await;
+        let load = || async { subscribe::Model::get_by_id(&db, 1).await.unwrap().unwrap() };
+
+        let report = SubscriptionAPI::refresh(&db, load().await).await.unwrap();
+        assert_eq!(report.changes.added, 2);
+
+        let report = SubscriptionAPI::refresh(&db, load().await).await.unwrap();
+        assert!(report.not_modified);
+        assert_eq!(report.changes, SubscriptionChanges::default());
+
+        let report = SubscriptionAPI::refresh(&db, load().await).await.unwrap();
+        assert_eq!(report.changes.removed, 1);
+
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v0\""));
+
+        let snapshots = subscription_snapshot::Model::fetch_by_subscribe_id(&db, 1)
+            .await
+            .unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].etag.as_deref(), Some("\"v2\""));
+
+        let report = SubscriptionAPI::rollback(&db, load().await, snapshots[1].id)
+            .await
+            .unwrap();
+        assert_eq!(report.changes.added, 1);
+        assert_eq!(
+            xray::Model::fetch_by_subscribe_id(&db, 1)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_without_nodes_keeps_previous_nodes() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sub", listener.local_addr().unwrap());
+        let good = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A";
+        let server = serve(listener, vec![Some(good), Some("<html>maintenance</html>")]);
+        let db = setup_db(&[&url]).await;
+        let load = || async { subscribe::Model::get_by_id(&db, 1).await.unwrap().unwrap() };
+
+        SubscriptionAPI::refresh(&db, load().await).await.unwrap();
+        assert!(SubscriptionAPI::refresh(&db, load().await).await.is_err());
+        server.join().unwrap();
+
+        assert_eq!(
+            xray::Model::fetch_by_subscribe_id(&db, 1)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        let snapshots = subscription_snapshot::Model::fetch_by_subscribe_id(&db, 1)
+            .await
+            .unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].etag.as_deref(), Some("\"v0\""));
+    }
+
+    #[tokio::test]
+    async fn test_cleared_validators_refresh_unconditionally() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sub", listener.local_addr().unwrap());
+        let body = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A";
+        let server = serve(listener, vec![Some(body), Some(body)]);
+        let db = setup_db(&[&url]).await;
+        let load = || async { subscribe::Model::get_by_id(&db, 1).await.unwrap().unwrap() };
+
+        SubscriptionAPI::refresh(&db, load().await).await.unwrap();
+        subscription_snapshot::Model::clear_validators(&db, 1)
+            .await
+            .unwrap();
+        let report = SubscriptionAPI::refresh(&db, load().await).await.unwrap();
+        assert!(!report.not_modified);
+
+        let requests = server.join().unwrap();
+        assert!(!requests[1].contains("if-none-match"));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refreshes_are_serialized() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sub", listener.local_addr().unwrap());
+        let body = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A";
+        // The second refresh must see the first one's snapshot and ask conditionally
+        let server = serve(listener, vec![Some(body), None]);
+        let db = setup_db(&[&url]).await;
+        let sub = subscribe::Model::get_by_id(&db, 1).await.unwrap().unwrap();
+
+        let (first, second) = tokio::join!(
+            SubscriptionAPI::refresh(&db, sub.clone()),
+            SubscriptionAPI::refresh(&db, sub),
+        );
+        let reports = [first.unwrap(), second.unwrap()];
+        assert_eq!(reports.iter().filter(|x| x.not_modified).count(), 1);
+        assert_eq!(reports.iter().map(|x| x.changes.added).sum::<usize>(), 1);
+        server.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reapply_filters_after_import() {
+        use crate::apis::parse_subscription::download_subscription_nodes;
+        use entity::subscribe::NodeFilters;
+
+        let db = setup_db(&[""]).await;
+        let text = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#HK\n\
+                    vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@b.example.com:443?type=tcp#JP";
+        let source = SubscriptionSource::new("", Some(text.into())).unwrap();
+        let (mut nodes, download) =
+            download_subscription_nodes(&source, &FetchOptions::default(), &NodeFilters::default())
+                .await
+                .unwrap();
+        nodes.set_subscribe_id(1);
+        nodes.insert(&db).await.unwrap();
+        SubscriptionAPI::save_snapshot(&db, 1, &download).await.unwrap();
+
+        let mut sub = subscribe::Model::get_by_id(&db, 1).await.unwrap().unwrap();
+        sub.filters = Some(NodeFilters {
+            exclude: Some("JP".into()),
+            ..Default::default()
+        });
+        let report = SubscriptionAPI::reapply_filters(&db, sub).await.unwrap();
+        assert_eq!(report.changes.removed, 1);
+        let remaining = xray::Model::fetch_by_subscribe_id(&db, 1).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name, "HK");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_subscription_keeps_nodes() {
+        let db = setup_db(&["https://example.com/sub"]).await;
+        let link = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A";
+        parse_subscription_text(link).sync(&db, 1).await.unwrap();
+        let manual: xray::Model = link.replace("#A", "#manual").parse().unwrap();
+        manual.insert_one(&db).await.unwrap();
+        assert_eq!(xray::Model::fetch_enabled(&db).await.unwrap().len(), 2);
+
+        let mut sub: subscribe::ActiveModel = subscribe::Model::get_by_id(&db, 1)
+            .await
+            .unwrap()
+            .unwrap()
+            .into();
+        sub.enabled = Set(false);
+        sub.update(&db).await.unwrap();
+
+        let enabled = xray::Model::fetch_enabled(&db).await.unwrap();
+        assert_eq!(enabled.len(), 1);
+        assert_eq!(enabled[0].name, "manual");
+        assert_eq!(
+            xray::Model::fetch_by_subscribe_id(&db, 1)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! Fixtures shared by the database tests.
+
+use chrono::Utc;
+use entity::subscribe::{self, FetchMode, SubscriptionKind};
+use entity::{base_config, hysteria, subscription_snapshot, xray};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, Schema, Set};
+
+/// In-memory SQLite database with all tables and one enabled remote subscription
+/// per URL; their ids start at 1 in the given order.
+pub async fn setup_db(urls: &[&str]) -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let backend = db.get_database_backend();
+    for statement in [
+        schema.create_table_from_entity(base_config::Entity),
+        schema.create_table_from_entity(subscribe::Entity),
+        schema.create_table_from_entity(subscription_snapshot::Entity),
+        schema.create_table_from_entity(xray::Entity),
+        schema.create_table_from_entity(hysteria::Entity),
+    ] {
+        db.execute(backend.build(&statement)).await.unwrap();
+    }
+    for url in urls {
+        subscribe::ActiveModel {
+            url: Set(url.to_string()),
+            name: Set(url.to_string()),
+            enabled: Set(true),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            fetch_mode: Set(FetchMode::Direct),
+            kind: Set(SubscriptionKind::Remote),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+    }
+    db
+}
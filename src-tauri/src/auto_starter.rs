@@ -81,8 +81,8 @@ impl AutoStarter {
         info!("Starting auto-measure and fastest server selection");
 
         // Fetch all proxies
-        let xray_records = xray::Model::fetch_all(&self.db).await?;
-        let hysteria_record = hysteria::Model::first(&self.db).await?;
+        let xray_records = xray::Model::fetch_enabled(&self.db).await?;
+        let hysteria_record = hysteria::Model::first_enabled(&self.db).await?;
 
         let hysteria_records = if let Some(record) = hysteria_record {
             vec![record]
@@ -213,7 +213,7 @@ impl AutoStarter {
     /// Fallback to the first available proxy when all delay tests fail.
     async fn fallback_to_first_proxy(&self) -> Result<AutoStartResult> {
         // Try hysteria first
-        if let Ok(Some(hysteria_record)) = hysteria::Model::first(&self.db).await {
+        if let Ok(Some(hysteria_record)) = hysteria::Model::first_enabled(&self.db).await {
             info!("Using fallback: hysteria proxy ID={}", hysteria_record.id);
             self.start_server_by_id(hysteria_record.id as u32, ProxyType::Hysteria2)
                 .await?;
@@ -224,7 +224,7 @@ impl AutoStarter {
         }
 
         // Then try xray
-        let xray_records = xray::Model::fetch_all(&self.db).await?;
+        let xray_records = xray::Model::fetch_enabled(&self.db).await?;
         if !xray_records.is_empty() {
             let first = &xray_records[0];
             info!("Using fallback: xray proxy ID={}", first.id);
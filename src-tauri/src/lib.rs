@@ -19,7 +19,9 @@ mod auto_starter;
 mod config_converter;
 mod proxy;
 mod rules;
+mod share_server;
 mod state;
+mod subscription_scheduler;
 mod tauri_apis;
 mod tauri_event_handler;
 mod tauri_init;
@@ -137,6 +139,7 @@ pub fn run() {
             db: Default::default(),
         });
     let builder = builder.manage(ProcessManagerState::default());
+    let builder = builder.manage(share_server::ShareServerState::default());
     let builder = builder
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_clipboard_manager::init())
@@ -158,8 +161,15 @@ pub fn run() {
         proxy_api::get_xray_by_id,
         proxy_api::batch_get_subscriptions,
         proxy_api::refresh_subscriptions,
-        proxy_api::auto_refresh_active_subscription,
         proxy_api::import_subscription,
+        proxy_api::import_singbox_config,
+        proxy_api::export_share_links,
+        proxy_api::find_duplicate_nodes,
+        proxy_api::get_node_qr_code,
+        proxy_api::import_qr_code_image,
+        proxy_api::import_share_links,
+        proxy_api::import_xray_config,
+        proxy_api::export_xray_config_json,
         proxy_api::refresh_xray_subscription,
         proxy_api::import_xray_subscribe,
         proxy_api::proxies_delay_test,
@@ -169,7 +179,11 @@ pub fn run() {
         crate::tauri_apis::subscription::update_subscription,
         crate::tauri_apis::subscription::delete_subscription,
         crate::tauri_apis::subscription::switch_subscription,
+        crate::tauri_apis::subscription::set_subscription_enabled,
         crate::tauri_apis::subscription::refresh_subscription,
+        crate::tauri_apis::subscription::preview_subscription,
+        crate::tauri_apis::subscription::get_subscription_snapshots,
+        crate::tauri_apis::subscription::rollback_subscription,
         // Common commands
         common_api::query_base_config,
         common_api::update_base_config,
@@ -183,6 +197,10 @@ pub fn run() {
         common_api::test_current_proxy,
         common_api::get_log_level,
         common_api::set_log_level,
+        common_api::set_skip_duplicate_nodes,
+        common_api::get_share_server,
+        common_api::set_share_server,
+        common_api::reset_share_server_token,
         // Server commands
         server_api::start_proxy_server,
         server_api::stop_proxy_server,
@@ -0,0 +1,321 @@
+//! Optional local HTTP endpoint serving the enabled nodes as a subscription.
+//!
+//! Phones and other machines on the LAN subscribe to
+//! `http://<local_ip>:<share_server_port>/sub?token=<token>` instead of each fetching
+//! every provider. `format` selects a base64 share-link list (default), a Clash
+//! profile or a sing-box config.
+
+use anyhow::{anyhow, Result};
+use entity::base_config;
+use hyper::body::Incoming;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use log::{debug, info, warn};
+use rand::distr::Alphanumeric;
+use rand::Rng;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tokio::net::TcpListener;
+
+use crate::apis::clash::export_clash_config;
+use crate::apis::parse_subscription::SubscriptionNodes;
+use crate::apis::share_links::{enabled_nodes, encode_subscription, share_links};
+use crate::apis::singbox::export_singbox_config;
+
+pub const SHARE_SERVER_PATH: &str = "/sub";
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionFormat {
+    Base64,
+    Clash,
+    SingBox,
+}
+
+impl SubscriptionFormat {
+    pub const ALL: [SubscriptionFormat; 3] = [Self::Base64, Self::Clash, Self::SingBox];
+
+    /// Value of the `format` query parameter, also accepting common client names.
+    pub fn from_param(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "base64" | "v2ray" => Some(Self::Base64),
+            "clash" | "mihomo" => Some(Self::Clash),
+            "singbox" | "sing-box" => Some(Self::SingBox),
+            _ => None,
+        }
+    }
+
+    pub fn param(&self) -> &'static str {
+        match self {
+            Self::Base64 => "base64",
+            Self::Clash => "clash",
+            Self::SingBox => "singbox",
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Base64 => "text/plain; charset=utf-8",
+            Self::Clash => "text/yaml; charset=utf-8",
+            Self::SingBox => "application/json",
+        }
+    }
+
+    pub fn render(&self, nodes: &SubscriptionNodes) -> Result<String> {
+        match self {
+            Self::Base64 => Ok(encode_subscription(&share_links(nodes))),
+            Self::Clash => export_clash_config(nodes),
+            Self::SingBox => export_singbox_config(nodes),
+        }
+    }
+}
+
+pub fn generate_token() -> String {
+    rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Subscription URLs of the share server, one per format.
+pub fn share_urls(config: &base_config::Model) -> Vec<String> {
+    SubscriptionFormat::ALL
+        .iter()
+        .map(|format| {
+            format!(
+                "http://{}:{}{}?token={}&format={}",
+                config.local_ip,
+                config.share_server_port,
+                SHARE_SERVER_PATH,
+                config.share_server_token,
+                format.param()
+            )
+        })
+        .collect()
+}
+
+/// Handle of the running share server, managed as Tauri state.
+#[derive(Default)]
+pub struct ShareServerState {
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ShareServerState {
+    pub fn is_running(&self) -> bool {
+        self.handle.lock().unwrap().is_some()
+    }
+
+    pub fn stop(&self) {
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            handle.abort();
+            info!("Share server stopped");
+        }
+    }
+
+    /// Stop the running server and start it again if it is enabled in the base config,
+    /// picking up a changed address, port or token.
+    pub async fn restart(&self, db: &DatabaseConnection) -> Result<()> {
+        self.stop();
+        let config = base_config::Model::first(db)
+            .await?
+            .ok_or_else(|| anyhow!("Base config not found"))?;
+        if !config.share_server_enabled {
+            return Ok(());
+        }
+        if config.share_server_token.is_empty() {
+            return Err(anyhow!("Share server token is not set"));
+        }
+        let address = (config.local_ip.as_str(), config.share_server_port);
+        let listener = TcpListener::bind(address).await.map_err(|e| {
+            anyhow!(
+                "Failed to bind share server to {}:{}: {}",
+                config.local_ip,
+                config.share_server_port,
+                e
+            )
+        })?;
+        info!(
+            "Share server listening on {}:{}",
+            config.local_ip, config.share_server_port
+        );
+        let handle =
+            tauri::async_runtime::spawn(serve(listener, db.clone(), config.share_server_token));
+        *self.handle.lock().unwrap() = Some(handle);
+        Ok(())
+    }
+}
+
+async fn serve(listener: TcpListener, db: DatabaseConnection, token: String) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Share server failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let db = db.clone();
+        let token = token.clone();
+        tauri::async_runtime::spawn(async move {
+            let service = service_fn(move |request| respond(db.clone(), token.clone(), request));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("Share server connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn respond(
+    db: DatabaseConnection,
+    token: String,
+    request: Request<Incoming>,
+) -> Result<Response<String>, Infallible> {
+    let format = match route(request.uri().path(), request.uri().query(), &token) {
+        Ok(format) => format,
+        Err((status, message)) => return Ok(text_response(status, message.into())),
+    };
+    let body = match enabled_nodes(&db).await {
+        Ok(nodes) => format.render(&nodes),
+        Err(e) => Err(e),
+    };
+    let response = match body {
+        Ok(body) => {
+            let mut response = text_response(StatusCode::OK, body);
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static(format.content_type()),
+            );
+            response
+        }
+        Err(e) => {
+            warn!("Share server failed to render subscription: {}", e);
+            text_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        }
+    };
+    Ok(response)
+}
+
+/// Check path and token and pick the requested format.
+fn route(
+    path: &str,
+    query: Option<&str>,
+    token: &str,
+) -> Result<SubscriptionFormat, (StatusCode, &'static str)> {
+    if path != SHARE_SERVER_PATH {
+        return Err((StatusCode::NOT_FOUND, "not found"));
+    }
+    let params: HashMap<String, String> = query
+        .map(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    if params.get("token").map(String::as_str) != Some(token) {
+        return Err((StatusCode::FORBIDDEN, "invalid token"));
+    }
+    match params.get("format") {
+        Some(format) => SubscriptionFormat::from_param(format)
+            .ok_or((StatusCode::BAD_REQUEST, "unsupported format")),
+        None => Ok(SubscriptionFormat::Base64),
+    }
+}
+
+fn text_response(status: StatusCode, body: String) -> Response<String> {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        let token = "secret";
+        assert_eq!(
+            route("/sub", Some("token=secret"), token),
+            Ok(SubscriptionFormat::Base64)
+        );
+        assert_eq!(
+            route("/sub", Some("format=sing-box&token=secret"), token),
+            Ok(SubscriptionFormat::SingBox)
+        );
+        assert_eq!(
+            route("/sub", Some("token=wrong&format=clash"), token)
+                .unwrap_err()
+                .0,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            route("/sub", None, token).unwrap_err().0,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            route("/sub", Some("token=secret&format=quantumult"), token)
+                .unwrap_err()
+                .0,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            route("/", Some("token=secret"), token).unwrap_err().0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(generate_token().len(), TOKEN_LENGTH);
+    }
+
+    #[tokio::test]
+    async fn test_serve_enabled_nodes() {
+        use crate::apis::parse_subscription::parse_subscription_text;
+        use crate::apis::test_utils::setup_db;
+
+        let db = setup_db(&[]).await;
+        let text = "vless://b85798ef-e9dc-46a4-9a87-8da4499d36d0@a.example.com:443?type=tcp#A\n\
+                    hy2://secret@b.example.com:443#B";
+        parse_subscription_text(text).insert(&db).await.unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!(
+            "http://{}{}",
+            listener.local_addr().unwrap(),
+            SHARE_SERVER_PATH
+        );
+        let handle = tokio::spawn(serve(listener, db, "secret".into()));
+
+        let body = reqwest::get(format!("{base}?token=secret"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let nodes = parse_subscription_text(&body);
+        assert_eq!((nodes.xray.len(), nodes.hysteria.len()), (1, 1));
+
+        let response = reqwest::get(format!("{base}?token=secret&format=clash"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/yaml; charset=utf-8");
+        assert!(response.text().await.unwrap().contains("type: hysteria2"));
+
+        let response = reqwest::get(format!("{base}?token=guess")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN.as_u16());
+        handle.abort();
+    }
+}
@@ -0,0 +1,233 @@
+//! Background scheduler that keeps subscriptions up to date.
+//!
+//! The scheduler runs as a tokio task independent of any window, so it keeps
+//! refreshing while the app is hidden to the tray. Each subscription is refreshed
+//! on its own interval (the provider's `profile-update-interval`, falling back to
+//! `base_config.update_interval`); failures are retried with exponential backoff.
+
+use chrono::{DateTime, Duration, Utc};
+use entity::subscribe::SubscriptionKind;
+use entity::{base_config, subscribe};
+use log::{info, warn};
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter};
+
+use crate::apis::parse_subscription::ImportReport;
+use crate::apis::subscription_apis::SubscriptionAPI;
+
+/// Emitted after a subscription was refreshed in the background.
+pub const SUBSCRIPTION_REFRESHED_EVENT: &str = "subscription-refreshed";
+/// Emitted when a background refresh failed.
+pub const SUBSCRIPTION_REFRESH_FAILED_EVENT: &str = "subscription-refresh-failed";
+
+const CHECK_INTERVAL_SECS: u64 = 60;
+const DEFAULT_UPDATE_INTERVAL_HOURS: i32 = 3;
+const FIRST_RETRY_SECS: i64 = 60;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SubscriptionRefreshedPayload {
+    pub id: i32,
+    pub name: String,
+    pub report: ImportReport,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SubscriptionRefreshFailedPayload {
+    pub id: i32,
+    pub name: String,
+    pub error: String,
+    pub attempts: u32,
+    pub next_retry_at: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RetryState {
+    attempts: u32,
+    next_retry_at: DateTime<Utc>,
+}
+
+pub struct SubscriptionScheduler {
+    app: AppHandle,
+    db: DatabaseConnection,
+    retries: HashMap<i32, RetryState>,
+}
+
+impl SubscriptionScheduler {
+    pub fn new(app: AppHandle, db: DatabaseConnection) -> Self {
+        Self {
+            app,
+            db,
+            retries: HashMap::new(),
+        }
+    }
+
+    /// Start the scheduler loop on the async runtime.
+    pub fn spawn(mut self) {
+        tauri::async_runtime::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+            loop {
+                interval.tick().await;
+                self.tick().await;
+            }
+        });
+    }
+
+    async fn tick(&mut self) {
+        let default_interval = match base_config::Model::first(&self.db).await {
+            Ok(Some(config)) => config.update_interval,
+            Ok(None) => DEFAULT_UPDATE_INTERVAL_HOURS,
+            Err(e) => {
+                warn!("Subscription scheduler failed to read base config: {}", e);
+                return;
+            }
+        };
+        let subscriptions = match subscribe::Model::fetch_all(&self.db).await {
+            Ok(subscriptions) => subscriptions,
+            Err(e) => {
+                warn!("Subscription scheduler failed to load subscriptions: {}", e);
+                return;
+            }
+        };
+        // Forget retry state of deleted subscriptions
+        self.retries
+            .retain(|id, _| subscriptions.iter().any(|sub| sub.id == *id));
+
+        let now = Utc::now();
+        for sub in subscriptions {
+            let Some(interval) = update_interval(&sub, default_interval) else {
+                continue;
+            };
+            let retry = self.retries.get(&sub.id).copied();
+            if !is_due(sub.last_sync_at, interval, retry, now) {
+                continue;
+            }
+            self.refresh(sub, interval).await;
+        }
+    }
+
+    async fn refresh(&mut self, sub: subscribe::Model, interval: Duration) {
+        let id = sub.id;
+        let name = sub.name.clone();
+        match SubscriptionAPI::refresh(&self.db, sub).await {
+            Ok(report) => {
+                info!(
+                    "Subscription {} refreshed in background: {:?}",
+                    id, report.changes
+                );
+                self.retries.remove(&id);
+                let _ = self.app.emit(
+                    SUBSCRIPTION_REFRESHED_EVENT,
+                    SubscriptionRefreshedPayload { id, name, report },
+                );
+            }
+            Err(e) => {
+                let attempts = self.retries.get(&id).map_or(0, |x| x.attempts) + 1;
+                let next_retry_at = Utc::now() + retry_delay(attempts, interval);
+                warn!(
+                    "Background refresh of subscription {} failed (attempt {}): {}",
+                    id, attempts, e
+                );
+                self.retries.insert(
+                    id,
+                    RetryState {
+                        attempts,
+                        next_retry_at,
+                    },
+                );
+                let _ = self.app.emit(
+                    SUBSCRIPTION_REFRESH_FAILED_EVENT,
+                    SubscriptionRefreshFailedPayload {
+                        id,
+                        name,
+                        error: e.to_string(),
+                        attempts,
+                        next_retry_at: next_retry_at.to_rfc3339(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Refresh interval of a subscription, `None` if auto refresh is disabled, the
+/// subscription is disabled or it is pasted text that can't change on its own.
+fn update_interval(sub: &subscribe::Model, default_hours: i32) -> Option<Duration> {
+    if !sub.enabled || sub.kind == SubscriptionKind::Text {
+        return None;
+    }
+    let hours = sub
+        .update_interval
+        .filter(|x| *x > 0)
+        .unwrap_or(default_hours);
+    if hours > 0 {
+        Some(Duration::hours(hours as i64))
+    } else {
+        None
+    }
+}
+
+fn is_due(
+    last_sync_at: Option<DateTime<Utc>>,
+    interval: Duration,
+    retry: Option<RetryState>,
+    now: DateTime<Utc>,
+) -> bool {
+    if let Some(retry) = retry {
+        return now >= retry.next_retry_at;
+    }
+    match last_sync_at {
+        Some(last_sync_at) => now - last_sync_at >= interval,
+        None => true,
+    }
+}
+
+/// Exponential backoff starting at one minute, never longer than the refresh interval.
+fn retry_delay(attempts: u32, interval: Duration) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    let delay = Duration::seconds(FIRST_RETRY_SECS * (1i64 << exponent));
+    delay.min(interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_due() {
+        let now = Utc::now();
+        let interval = Duration::hours(3);
+        assert!(is_due(None, interval, None, now));
+        assert!(!is_due(Some(now - Duration::hours(2)), interval, None, now));
+        assert!(is_due(Some(now - Duration::hours(3)), interval, None, now));
+
+        let retry = RetryState {
+            attempts: 1,
+            next_retry_at: now + Duration::seconds(30),
+        };
+        assert!(!is_due(
+            Some(now - Duration::hours(5)),
+            interval,
+            Some(retry),
+            now
+        ));
+        assert!(is_due(
+            Some(now - Duration::hours(5)),
+            interval,
+            Some(retry),
+            now + Duration::seconds(30)
+        ));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let interval = Duration::hours(1);
+        assert_eq!(retry_delay(1, interval), Duration::minutes(1));
+        assert_eq!(retry_delay(2, interval), Duration::minutes(2));
+        assert_eq!(retry_delay(4, interval), Duration::minutes(8));
+        assert_eq!(retry_delay(10, interval), interval);
+        assert_eq!(retry_delay(100, interval), interval);
+    }
+}
@@ -1,10 +1,12 @@
 use crate::apis::common_apis::CommonAPI;
 use crate::proxy::delay::kitty_current_proxy_delay;
 use crate::rules::Rule;
+use crate::share_server::{generate_token, share_urls, ShareServerState};
 use crate::state::DatabaseState;
 use crate::types::{CommandResult, KittyResponse};
 use entity::base_config;
 use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tauri::{Manager, State};
 use tauri_plugin_clipboard_manager::ClipboardExt;
@@ -45,15 +47,97 @@ pub async fn query_base_config<'a>(
 #[tauri::command(rename_all = "snake_case")]
 pub async fn update_base_config<'a>(
     state: State<'a, DatabaseState>,
+    share_server: State<'a, ShareServerState>,
     record: base_config::Model,
 ) -> CommandResult<KittyResponse<base_config::Model>> {
     let db = state.get_db();
     let res = CommonAPI::update_base_config(&db, record).await?;
+    // local_ip may have changed
+    if share_server.is_running() {
+        share_server.restart(&db).await?;
+    }
     Ok(res)
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShareServerInfo {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    pub token: String,
+    pub urls: Vec<String>,
+}
+
+async fn share_server_info(
+    db: &DatabaseConnection,
+    share_server: &ShareServerState,
+) -> anyhow::Result<ShareServerInfo> {
+    let record = base_config::Model::first(db)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("base_config not exists"))?;
+    Ok(ShareServerInfo {
+        enabled: record.share_server_enabled,
+        running: share_server.is_running(),
+        port: record.share_server_port,
+        urls: share_urls(&record),
+        token: record.share_server_token,
+    })
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_share_server<'a>(
+    state: State<'a, DatabaseState>,
+    share_server: State<'a, ShareServerState>,
+) -> CommandResult<KittyResponse<ShareServerInfo>> {
+    let db = state.get_db();
+    let info = share_server_info(&db, &share_server).await?;
+    Ok(KittyResponse::from_data(info))
+}
+
+/// Enable or disable the local subscription endpoint, generating a token on first use.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_share_server<'a>(
+    state: State<'a, DatabaseState>,
+    share_server: State<'a, ShareServerState>,
+    enabled: bool,
+    port: Option<u16>,
+) -> CommandResult<KittyResponse<ShareServerInfo>> {
+    let db = state.get_db();
+    let mut record = base_config::Model::first(&db)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("base_config not exists"))?;
+    record.share_server_enabled = enabled;
+    if let Some(port) = port {
+        record.share_server_port = port;
+    }
+    if record.share_server_token.is_empty() {
+        record.share_server_token = generate_token();
+    }
+    record.update(&db).await?;
+    share_server.restart(&db).await?;
+    let info = share_server_info(&db, &share_server).await?;
+    Ok(KittyResponse::from_data(info))
+}
+
+/// Replace the token, invalidating subscription URLs handed out so far.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn reset_share_server_token<'a>(
+    state: State<'a, DatabaseState>,
+    share_server: State<'a, ShareServerState>,
+) -> CommandResult<KittyResponse<ShareServerInfo>> {
+    let db = state.get_db();
+    let mut record = base_config::Model::first(&db)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("base_config not exists"))?;
+    record.share_server_token = generate_token();
+    record.update(&db).await?;
+    share_server.restart(&db).await?;
+    let info = share_server_info(&db, &share_server).await?;
+    Ok(KittyResponse::from_data(info))
+}
+
 /// Get the rules file path from the app data directory
-fn get_rules_path<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
+pub(crate) fn get_rules_path<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
     app_handle
         .path()
         .app_data_dir()
@@ -153,6 +237,21 @@ pub async fn set_log_level<'a>(
     Ok(KittyResponse::default())
 }
 
+/// Skip nodes whose server and port are already imported when refreshing subscriptions.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_skip_duplicate_nodes<'a>(
+    state: State<'a, DatabaseState>,
+    skip_duplicate_nodes: bool,
+) -> CommandResult<KittyResponse<()>> {
+    let db = state.get_db();
+    let mut record = base_config::Model::first(&db)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("base_config not exists"))?;
+    record.skip_duplicate_nodes = skip_duplicate_nodes;
+    record.update(&db).await?;
+    Ok(KittyResponse::default())
+}
+
 /// Export rules as JSON string
 #[tauri::command(rename_all = "snake_case")]
 pub async fn export_rules<'a, R: Runtime>(
@@ -112,7 +112,7 @@ pub async fn start_all_servers<'a>(
     }
 
     // Process hysteria record if exists
-    let hysteria_record = hysteria_entity::Model::first(&db).await?;
+    let hysteria_record = hysteria_entity::Model::first_enabled(&db).await?;
     if let Some(hysteria_record) = hysteria_record {
         let yaml_config = ShoesConfigConverter::hysteria_to_socks_http_yaml(
             &hysteria_record,
@@ -132,12 +132,12 @@ pub async fn start_all_servers<'a>(
 
     // Process xray records
     let xray_records = if xray_id.is_none() {
-        xray_entity::Model::fetch_all(&db).await?
+        xray_entity::Model::fetch_enabled(&db).await?
     } else {
         if let Some(item) = xray_entity::Model::get_by_id(&db, xray_id.unwrap()).await? {
             vec![item]
         } else {
-            xray_entity::Model::fetch_all(&db).await?
+            xray_entity::Model::fetch_enabled(&db).await?
         }
     };
 
@@ -4,14 +4,29 @@
 //! The actual proxy serving is done via the shoes library.
 
 use anyhow::anyhow;
+use base64::engine::general_purpose;
+use base64::Engine;
+use entity::subscribe::{NodeFilters, SubscriptionKind};
 use entity::{hysteria, xray};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::str::FromStr;
-use tauri::State;
-
+use std::path::Path;
+use tauri::{AppHandle, Runtime, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::apis::parse_subscription::{
+    FetchOptions, ImportReport, SubscriptionNodes, SubscriptionSource,
+};
+use crate::apis::duplicates::{find_duplicates, merge_duplicates, DuplicateReport};
+use crate::apis::qr_codes::{decode_image_file, render_png, render_svg, QrImageFormat};
+use crate::apis::share_links::{encode_subscription, share_links, NodeSelection};
+use crate::apis::singbox::parse_singbox_config;
+use crate::apis::subscription_apis::SubscriptionAPI;
+use crate::apis::xray_config::{export_xray_config, parse_xray_config};
 use crate::proxy::delay::{kitty_proxies_delay, ProxyInfo};
-use crate::state::DatabaseState;
+use crate::state::{DatabaseState, ProcessManagerState};
+use crate::tauri_apis::common::get_rules_path;
+use crate::tauri_apis::subscription::new_subscription;
 use crate::types::{CommandResult, KittyResponse};
 
 /// Unified proxy type for frontend.
@@ -68,13 +83,13 @@ pub async fn get_all_proxies<'a>(
     let mut proxies = Vec::new();
 
     // Get hysteria records
-    let hysteria_records = hysteria::Model::fetch_all(&db).await?;
+    let hysteria_records = hysteria::Model::fetch_enabled(&db).await?;
     for record in hysteria_records {
         proxies.push(Proxy::from(record));
     }
 
     // Get xray records
-    let xray_records = xray::Model::fetch_all(&db).await?;
+    let xray_records = xray::Model::fetch_enabled(&db).await?;
     for record in xray_records {
         proxies.push(Proxy::from(record));
     }
@@ -92,7 +107,7 @@ pub async fn get_all_hysterias<'a>(
     db_state: State<'a, DatabaseState>,
 ) -> CommandResult<KittyResponse<Vec<hysteria::Model>>> {
     let db = db_state.get_db();
-    let records = hysteria::Model::fetch_all(&db).await?;
+    let records = hysteria::Model::fetch_enabled(&db).await?;
     Ok(KittyResponse::from_data(records))
 }
 
@@ -150,7 +165,7 @@ pub async fn get_all_xrays<'a>(
     db_state: State<'a, DatabaseState>,
 ) -> CommandResult<KittyResponse<Vec<xray::Model>>> {
     let db = db_state.get_db();
-    let records = xray::Model::fetch_all(&db).await?;
+    let records = xray::Model::fetch_enabled(&db).await?;
     Ok(KittyResponse::from_data(records))
 }
 
@@ -214,162 +229,84 @@ pub async fn batch_get_subscriptions<'a>(
 
 /// Refresh subscriptions (legacy).
 /// If record_ids is provided, refresh only those subscriptions.
-/// Otherwise, refresh all subscriptions.
+/// Otherwise, refresh all enabled subscriptions.
+/// Returns an import report for every subscription that was refreshed.
 #[tauri::command(rename_all = "snake_case")]
 pub async fn refresh_subscriptions<'a>(
     db_state: State<'a, DatabaseState>,
     record_ids: Option<Vec<i32>>,
-) -> CommandResult<KittyResponse<()>> {
-    // Fetch subscriptions to refresh (release connection immediately after)
-    let subscriptions = {
-        let db = db_state.get_db();
-        if let Some(ids) = record_ids {
-            entity::subscribe::Model::fetch_by_ids(&db, ids).await?
-        } else {
-            entity::subscribe::Model::fetch_all(&db).await?
-        }
-    }; // db connection released here
-
-    if subscriptions.is_empty() {
-        return Ok(KittyResponse::default());
-    }
+) -> CommandResult<KittyResponse<Vec<ImportReport>>> {
+    let db = db_state.get_db();
+    let subscriptions = if let Some(ids) = record_ids {
+        entity::subscribe::Model::fetch_by_ids(&db, ids).await?
+    } else {
+        use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+        entity::subscribe::Entity::find()
+            .filter(entity::subscribe::Column::Enabled.eq(true))
+            .all(&db)
+            .await?
+    };
 
-    // Refresh each subscription
-    use sea_orm::{ModelTrait, TransactionTrait};
+    // Refresh each subscription, skipping the ones that fail
+    let mut reports = Vec::new();
     for subscribe_item in subscriptions {
-        // Download new subscription content (NO db connection held during network IO)
-        let subscriptions_result =
-            crate::apis::parse_subscription::download_subcriptions(&subscribe_item.url).await;
-
-        let new_subscriptions = match subscriptions_result {
-            Ok(subs) => subs,
+        let subscription_id = subscribe_item.id;
+        match SubscriptionAPI::refresh(&db, subscribe_item).await {
+            Ok(report) => reports.push(report),
             Err(e) => {
                 log::warn!(
-                    "Failed to download subscription (id: {}): {}",
-                    subscribe_item.id,
-                    e
-                );
-                continue; // Skip this subscription and continue with others
-            }
-        };
-
-        // Only get db connection after network download completes
-        let db = db_state.get_db();
-        // Start transaction for this subscription
-        let txn = match db.begin().await {
-            Ok(t) => t,
-            Err(e) => {
-                log::error!(
-                    "Failed to start transaction for subscription (id: {}): {}",
-                    subscribe_item.id,
-                    e
-                );
-                continue;
-            }
-        };
-
-        // Get and delete old xray records
-        let old_xray_records = subscribe_item
-            .find_related(xray::Entity)
-            .all(&db)
-            .await
-            .unwrap_or_default();
-
-        let xray_ids: Vec<i32> = old_xray_records.iter().map(|x| x.id).collect();
-        if !xray_ids.is_empty() {
-            if let Err(e) = xray::Model::delete_by_ids(&txn, xray_ids).await {
-                log::error!(
-                    "Failed to delete old xray records for subscription (id: {}): {}",
-                    subscribe_item.id,
-                    e
-                );
-                continue;
-            }
-        }
-
-        // Parse and insert new xray records
-        let mut xray_models = Vec::new();
-        for line in new_subscriptions {
-            if !line.is_xray() {
-                continue;
-            }
-            if let Ok(mut xray_model) = xray::Model::from_str(&line.line.trim()) {
-                xray_model.subscribe_id = Some(subscribe_item.id);
-                xray_models.push(xray_model);
-            }
-        }
-
-        if !xray_models.is_empty() {
-            if let Err(e) = xray::Model::insert_many(&txn, xray_models).await {
-                log::error!(
-                    "Failed to insert new xray records for subscription (id: {}): {}",
-                    subscribe_item.id,
+                    "Failed to refresh subscription (id: {}): {}",
+                    subscription_id,
                     e
                 );
-                continue;
             }
         }
+    }
 
-        // Commit transaction
-        if let Err(e) = txn.commit().await {
-            log::error!(
-                "Failed to commit transaction for subscription (id: {}): {}",
-                subscribe_item.id,
-                e
-            );
-            continue;
-        }
-
-        // Update last_sync_at and updated_at after successful refresh
-        use chrono::Utc;
-        use sea_orm::{ActiveModelTrait, Set};
-        let subscription_id = subscribe_item.id;
-        let mut active_model: entity::subscribe::ActiveModel = subscribe_item.into();
-        active_model.last_sync_at = Set(Some(Utc::now()));
-        active_model.updated_at = Set(Utc::now());
-        if let Err(e) = active_model.update(&db).await {
-            log::error!(
-                "Failed to update subscription timestamps (id: {}): {}",
-                subscription_id,
-                e
-            );
-        }
-
-        // Explicitly drop db connection to release it immediately
-        drop(db);
-    } // End of for loop
-
-    Ok(KittyResponse::default())
+    Ok(KittyResponse::from_data(reports))
 }
 
-/// Import subscription from URL.
+/// Import subscription from an `http(s)://` or `file://` URL, or from pasted `content`.
 #[tauri::command(rename_all = "snake_case")]
 pub async fn import_subscription<'a>(
     db_state: State<'a, DatabaseState>,
     url: String,
-) -> CommandResult<KittyResponse<()>> {
+    name: Option<String>,
+    options: Option<FetchOptions>,
+    filters: Option<NodeFilters>,
+    content: Option<String>,
+) -> CommandResult<KittyResponse<ImportReport>> {
     let db = db_state.get_db();
 
-    // Validate URL format - must be http/https
-    if !url.starts_with("http://") && !url.starts_with("https://") {
-        return Err(anyhow!("Only HTTP/HTTPS subscription URLs are supported").into());
-    }
+    // Validate URL format - must be http/https or file
+    let source = SubscriptionSource::new(&url, content.clone())?;
 
     // Check if subscription URL already exists
     use sea_orm::ColumnTrait;
     use sea_orm::EntityTrait;
     use sea_orm::QueryFilter;
-    let existing = entity::subscribe::Entity::find()
-        .filter(entity::subscribe::Column::Url.eq(&url))
-        .one(&db)
-        .await?;
+    if source.kind() != SubscriptionKind::Text {
+        let existing = entity::subscribe::Entity::find()
+            .filter(entity::subscribe::Column::Url.eq(&url))
+            .one(&db)
+            .await?;
 
-    if existing.is_some() {
-        return Err(anyhow!("Subscription URL already exists").into());
+        if existing.is_some() {
+            return Err(anyhow!("Subscription URL already exists").into());
+        }
     }
 
     // Download and parse subscription
-    let subscriptions = crate::apis::parse_subscription::download_subcriptions(&url).await?;
+    let options = options.unwrap_or_default().with_local_proxy(&db).await?;
+    let filters = filters.unwrap_or_default();
+    let (mut nodes, download) =
+        crate::apis::parse_subscription::download_subscription_nodes(&source, &options, &filters)
+            .await?;
+    if nodes.is_empty() {
+        return Err(no_proxies_error("subscription", &nodes.report).into());
+    }
+    nodes.skip_duplicates(&db, None).await?;
+    let mut report = nodes.take_report();
 
     // Start transaction
     use sea_orm::TransactionTrait;
@@ -378,112 +315,217 @@ pub async fn import_subscription<'a>(
     // Create subscription record
     use sea_orm::ActiveModelTrait;
     use sea_orm::Set;
-    let subscribe = entity::subscribe::ActiveModel {
-        url: Set(url.clone()),
-        ..Default::default()
-    };
+    let name = name.unwrap_or_else(|| url.clone());
+    let mut subscribe = new_subscription(name, url, source.kind(), content);
+    subscribe.last_sync_at = Set(Some(chrono::Utc::now()));
+    download.meta.apply(&mut subscribe);
+    options.apply(&mut subscribe);
+    subscribe.filters = Set(Some(filters));
     let subscribe_record = subscribe.insert(&txn).await?;
+    // First snapshot, so filters can be re-applied and the next refresh is conditional
+    SubscriptionAPI::save_snapshot(&txn, subscribe_record.id, &download).await?;
 
-    // Parse and insert xray records
-    let mut xray_models = Vec::new();
-    for line in subscriptions {
-        if !line.is_xray() {
-            continue;
-        }
-        if let Ok(mut xray_model) = xray::Model::from_str(&line.line.trim()) {
-            xray_model.subscribe_id = Some(subscribe_record.id);
-            xray_models.push(xray_model);
-        }
-    }
+    // Insert parsed records
+    report.subscribe_id = Some(subscribe_record.id);
+    report.changes.added = nodes.xray.len() + nodes.hysteria.len();
+    nodes.set_subscribe_id(subscribe_record.id);
+    nodes.insert(&txn).await?;
+
+    // Commit transaction
+    txn.commit().await?;
+
+    Ok(KittyResponse::from_data(report))
+}
 
-    if xray_models.is_empty() {
-        return Err(anyhow!("No valid xray proxies found in subscription").into());
+/// Import the proxies of a sing-box config file as manually added nodes.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_singbox_config<'a>(
+    db_state: State<'a, DatabaseState>,
+    path: String,
+) -> CommandResult<KittyResponse<ImportReport>> {
+    let text = std::fs::read_to_string(&path)?;
+    let nodes = parse_singbox_config(&text)
+        .ok_or_else(|| anyhow!("Not a valid sing-box config: missing outbounds"))?;
+    if nodes.is_empty() {
+        return Err(no_proxies_error("sing-box config", &nodes.report).into());
     }
+    let report = nodes.import_manual(&db_state.get_db()).await?;
+    Ok(KittyResponse::from_data(report))
+}
 
-    xray::Model::insert_many(&txn, xray_models).await?;
+/// Import the proxy outbounds of an xray-core config file as manually added nodes.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_xray_config<'a>(
+    db_state: State<'a, DatabaseState>,
+    path: String,
+) -> CommandResult<KittyResponse<ImportReport>> {
+    let text = std::fs::read_to_string(&path)?;
+    let nodes = parse_xray_config(&text)
+        .ok_or_else(|| anyhow!("Not a valid xray config: missing outbounds"))?;
+    if nodes.is_empty() {
+        return Err(no_proxies_error("xray config", &nodes.report).into());
+    }
+    let report = nodes.import_manual(&db_state.get_db()).await?;
+    Ok(KittyResponse::from_data(report))
+}
 
-    // Commit transaction
-    txn.commit().await?;
+/// Export the selected xray nodes as a stock xray-core `config.json`, with inbounds on
+/// our http/socks ports and routing from the custom rules.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_xray_config_json<'a, R: Runtime>(
+    app_handle: AppHandle<R>,
+    db_state: State<'a, DatabaseState>,
+    selection: NodeSelection,
+) -> CommandResult<KittyResponse<String>> {
+    let db = db_state.get_db();
+    let nodes = selection.load(&db).await?;
+    let config = entity::base_config::Model::first(&db)
+        .await?
+        .ok_or_else(|| anyhow!("base_config not exists"))?;
+    let rules = crate::rules::read_rules_file(&get_rules_path(&app_handle))?;
+    let json = export_xray_config(&nodes, &config, &rules)?;
+    Ok(KittyResponse::from_data(json))
+}
 
-    Ok(KittyResponse::default())
+/// Error for an import without any usable node, naming the first skipped line.
+fn no_proxies_error(source: &str, report: &ImportReport) -> anyhow::Error {
+    match report.skipped.first() {
+        Some(skipped) => anyhow!(
+            "No valid proxies found in {}: {} of {} lines skipped, line {}: {}",
+            source,
+            report.skipped.len(),
+            report.total_lines,
+            skipped.line,
+            skipped.reason
+        ),
+        None => anyhow!("No valid proxies found in {}", source),
+    }
 }
 
-/// Auto-refresh active subscription if needed (based on update_interval).
-/// This is the smart refresh logic that checks last_sync_at before refreshing.
+/// Report enabled nodes that duplicate each other across subscriptions and manual
+/// nodes, deleting all but one node of each group when `merge` is set.
 #[tauri::command(rename_all = "snake_case")]
-pub async fn auto_refresh_active_subscription<'a>(
+pub async fn find_duplicate_nodes<'a>(
     db_state: State<'a, DatabaseState>,
-) -> CommandResult<KittyResponse<()>> {
-    use chrono::Utc;
-    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
-
-    // Get update interval from base_config
-    let update_interval_hours = {
-        let db = db_state.get_db();
-        let config = entity::base_config::Model::first(&db).await?;
-        match config {
-            Some(c) => c.update_interval,
-            None => 3, // Default to 3 hours if no config
-        }
+    process_manager: State<'a, ProcessManagerState>,
+    merge: Option<bool>,
+) -> CommandResult<KittyResponse<DuplicateReport>> {
+    let db = db_state.get_db();
+    let active_id = *process_manager.active_proxy_id.lock().await;
+    let active_type = process_manager.active_proxy_type.lock().await.clone();
+    let active = match (active_type.as_deref(), active_id) {
+        (Some(proxy_type), Some(id)) => Some((proxy_type, id as i32)),
+        _ => None,
     };
-
-    // Find active subscription
-    let active_subscription = {
-        let db = db_state.get_db();
-        entity::subscribe::Entity::find()
-            .filter(entity::subscribe::Column::IsActive.eq(true))
-            .one(&db)
-            .await?
+    let groups = find_duplicates(&db, active).await?;
+    let removed = if merge.unwrap_or(false) {
+        merge_duplicates(&db, &groups).await?
+    } else {
+        0
     };
+    Ok(KittyResponse::from_data(DuplicateReport { groups, removed }))
+}
 
-    let subscription = match active_subscription {
-        Some(sub) => sub,
-        None => {
-            log::info!("No active subscription found, skipping auto-refresh");
-            return Ok(KittyResponse::default());
-        }
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShareExport {
+    pub links: Vec<String>,
+    /// Base64 of the newline separated links, usable as a subscription body
+    pub subscription: String,
+}
+
+/// Export one node, a selection or a whole subscription as share links.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_share_links<'a>(
+    db_state: State<'a, DatabaseState>,
+    selection: NodeSelection,
+) -> CommandResult<KittyResponse<ShareExport>> {
+    let db = db_state.get_db();
+    let nodes = selection.load(&db).await?;
+    let links = share_links(&nodes);
+    if links.is_empty() {
+        return Err(anyhow!("No nodes to export").into());
+    }
+    let subscription = encode_subscription(&links);
+    Ok(KittyResponse::from_data(ShareExport {
+        links,
+        subscription,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NodeQrCode {
+    pub link: String,
+    pub format: QrImageFormat,
+    /// `data:` URL of the rendered image
+    pub data_url: String,
+}
+
+/// Render the share link of a node as a QR code image.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_node_qr_code<'a>(
+    db_state: State<'a, DatabaseState>,
+    proxy_type: String,
+    id: i32,
+    format: Option<QrImageFormat>,
+) -> CommandResult<KittyResponse<NodeQrCode>> {
+    let db = db_state.get_db();
+    let nodes = NodeSelection::single(&proxy_type, id)?.load(&db).await?;
+    let link = share_links(&nodes)
+        .pop()
+        .ok_or_else(|| anyhow!("Proxy not found"))?;
+    let format = format.unwrap_or(QrImageFormat::Png);
+    let data = match format {
+        QrImageFormat::Png => general_purpose::STANDARD.encode(render_png(&link)?),
+        QrImageFormat::Svg => general_purpose::STANDARD.encode(render_svg(&link)?),
     };
+    let data_url = format!("data:{};base64,{}", format.mime_type(), data);
+    Ok(KittyResponse::from_data(NodeQrCode {
+        link,
+        format,
+        data_url,
+    }))
+}
+
+/// Decode the QR codes in an image file (e.g. a screenshot) and import them as
+/// manually added nodes.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_qr_code_image<'a>(
+    db_state: State<'a, DatabaseState>,
+    path: String,
+) -> CommandResult<KittyResponse<ImportReport>> {
+    let contents = decode_image_file(Path::new(&path))?;
+    let nodes = SubscriptionNodes::from_share_links(&contents.join("\n"));
+    if nodes.is_empty() {
+        return Err(no_proxies_error("QR code", &nodes.report).into());
+    }
+    let report = nodes.import_manual(&db_state.get_db()).await?;
+    Ok(KittyResponse::from_data(report))
+}
 
-    // Check if refresh is needed
-    let should_refresh = match subscription.last_sync_at {
+/// Import every share link found in `text`, or in the clipboard when no text is
+/// given, as manually added nodes.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_share_links<'a, R: Runtime>(
+    app_handle: AppHandle<R>,
+    db_state: State<'a, DatabaseState>,
+    text: Option<String>,
+) -> CommandResult<KittyResponse<ImportReport>> {
+    let (text, source) = match text {
+        Some(text) => (text, "pasted text"),
         None => {
-            log::info!("Subscription {} has never been synced, will refresh", subscription.id);
-            true
-        }
-        Some(last_sync) => {
-            let now = Utc::now();
-            let duration_since_sync = now.signed_duration_since(last_sync);
-            let hours_since_sync = duration_since_sync.num_hours();
-
-            if hours_since_sync >= update_interval_hours as i64 {
-                log::info!(
-                    "Subscription {} last synced {} hours ago (threshold: {} hours), will refresh",
-                    subscription.id,
-                    hours_since_sync,
-                    update_interval_hours
-                );
-                true
-            } else {
-                log::info!(
-                    "Subscription {} last synced {} hours ago (threshold: {} hours), skipping refresh",
-                    subscription.id,
-                    hours_since_sync,
-                    update_interval_hours
-                );
-                false
-            }
+            let text = app_handle
+                .clipboard()
+                .read_text()
+                .map_err(|e| anyhow!("Failed to read the clipboard: {}", e))?;
+            (text, "clipboard")
         }
     };
-
-    if !should_refresh {
-        return Ok(KittyResponse::default());
+    let nodes = SubscriptionNodes::from_text(&text);
+    if nodes.is_empty() {
+        return Err(no_proxies_error(source, &nodes.report).into());
     }
-
-    // Refresh the subscription using existing logic
-    // Note: refresh_subscriptions will automatically update last_sync_at and updated_at
-    refresh_subscriptions(db_state, Some(vec![subscription.id])).await?;
-
-    Ok(KittyResponse::default())
+    let report = nodes.import_manual(&db_state.get_db()).await?;
+    Ok(KittyResponse::from_data(report))
 }
 
 /// Legacy alias for compatibility.
@@ -491,7 +533,7 @@ pub async fn auto_refresh_active_subscription<'a>(
 pub async fn refresh_xray_subscription<'a>(
     db_state: State<'a, DatabaseState>,
     record_ids: Option<Vec<i32>>,
-) -> CommandResult<KittyResponse<()>> {
+) -> CommandResult<KittyResponse<Vec<ImportReport>>> {
     refresh_subscriptions(db_state, record_ids).await
 }
 
@@ -500,8 +542,8 @@ pub async fn refresh_xray_subscription<'a>(
 pub async fn import_xray_subscribe<'a>(
     db_state: State<'a, DatabaseState>,
     url: String,
-) -> CommandResult<KittyResponse<()>> {
-    import_subscription(db_state, url).await
+) -> CommandResult<KittyResponse<ImportReport>> {
+    import_subscription(db_state, url, None, None, None).await
 }
 
 /// Test proxy delay.
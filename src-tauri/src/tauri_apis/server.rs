@@ -57,13 +57,13 @@ impl ServerManager {
         self.stop_all_servers().await;
 
         // Try to start hysteria server if exists
-        let hysteria_record = hysteria::Model::first(db).await?;
+        let hysteria_record = hysteria::Model::first_enabled(db).await?;
         if let Some(hysteria_record) = hysteria_record {
             self.start_hysteria_server(db, hysteria_record.id).await?;
         }
 
         // Try to start xray servers if any exist
-        let xray_records = xray::Model::fetch_all(db).await?;
+        let xray_records = xray::Model::fetch_enabled(db).await?;
         if !xray_records.is_empty() {
             // Start the first xray server
             self.start_xray_server(db, xray_records[0].id).await?;
@@ -1,13 +1,19 @@
 use anyhow::anyhow;
 use entity::subscribe;
+use entity::subscribe::{NodeFilters, SubscriptionKind};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
 use tauri::State;
 
+use crate::apis::parse_subscription::{
+    parse_subscription_text, FetchOptions, ImportReport, SubscriptionNodes,
+    SubscriptionSource,
+};
+use crate::apis::subscription_apis::SubscriptionAPI;
 use crate::state::DatabaseState;
+use crate::tauri_apis::proxy::Proxy;
 use crate::types::{CommandResult, KittyResponse};
-use entity::xray;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait, PaginatorTrait};
+use entity::{hysteria, subscription_snapshot, xray};
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, TransactionTrait, PaginatorTrait};
 
 // Re-export chrono::Utc from sea-orm
 use chrono::Utc;
@@ -17,11 +23,85 @@ pub struct SubscriptionInfo {
     pub id: i32,
     pub name: String,
     pub url: String,
-    pub is_active: bool,
+    pub kind: SubscriptionKind,
+    /// Body of a pasted-text subscription
+    pub content: Option<String>,
+    pub enabled: bool,
     pub node_count: i64,
     pub created_at: String,
     pub updated_at: String,
     pub last_sync_at: Option<String>,
+    pub upload: Option<i64>,
+    pub download: Option<i64>,
+    pub total: Option<i64>,
+    pub expire_at: Option<String>,
+    pub update_interval: Option<i32>,
+    pub options: FetchOptions,
+    pub filters: NodeFilters,
+}
+
+impl SubscriptionInfo {
+    fn new(sub: subscribe::Model, node_count: i64) -> Self {
+        let options = FetchOptions::from_model(&sub);
+        let filters = sub.filters.clone().unwrap_or_default();
+        Self {
+            id: sub.id,
+            name: sub.name,
+            url: sub.url,
+            kind: sub.kind,
+            content: sub.content,
+            enabled: sub.enabled,
+            node_count,
+            created_at: sub.created_at.to_rfc3339(),
+            updated_at: sub.updated_at.to_rfc3339(),
+            last_sync_at: sub.last_sync_at.map(|dt| dt.to_rfc3339()),
+            upload: sub.upload,
+            download: sub.download,
+            total: sub.total,
+            expire_at: sub.expire_at.map(|dt| dt.to_rfc3339()),
+            update_interval: sub.update_interval,
+            options,
+            filters,
+        }
+    }
+}
+
+/// A new enabled subscription record, created now and never synced
+pub(crate) fn new_subscription(
+    name: String,
+    url: String,
+    kind: SubscriptionKind,
+    content: Option<String>,
+) -> subscribe::ActiveModel {
+    use sea_orm::Set;
+    let now = Utc::now();
+    subscribe::ActiveModel {
+        name: Set(name),
+        url: Set(url),
+        kind: Set(kind),
+        content: Set(content),
+        enabled: Set(true),
+        created_at: Set(now),
+        updated_at: Set(now),
+        last_sync_at: Set(None),
+        ..Default::default()
+    }
+}
+
+/// Count xray and hysteria nodes that belong to a subscription
+async fn count_subscription_nodes<C>(db: &C, subscribe_id: i32) -> Result<i64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let xray_count = xray::Entity::find()
+        .filter(xray::Column::SubscribeId.eq(subscribe_id))
+        .count(db)
+        .await?;
+    let hysteria_count = hysteria::Entity::find()
+        .filter(hysteria::Column::SubscribeId.eq(subscribe_id))
+        .count(db)
+        .await?;
+    Ok((xray_count + hysteria_count) as i64)
 }
 
 /// Get all subscriptions with node count statistics
@@ -34,102 +114,83 @@ pub async fn get_all_subscriptions<'a>(
 
     let mut result = Vec::new();
     for sub in subscriptions {
-        let node_count = xray::Entity::find()
-            .filter(xray::Column::SubscribeId.eq(sub.id))
-            .count(&db)
-            .await?;
-
-        result.push(SubscriptionInfo {
-            id: sub.id,
-            name: sub.name,
-            url: sub.url,
-            is_active: sub.is_active,
-            node_count: node_count as i64,
-            created_at: sub.created_at.to_rfc3339(),
-            updated_at: sub.updated_at.to_rfc3339(),
-            last_sync_at: sub.last_sync_at.map(|dt| dt.to_rfc3339()),
-        });
+        let node_count = count_subscription_nodes(&db, sub.id).await?;
+        result.push(SubscriptionInfo::new(sub, node_count));
     }
 
     Ok(KittyResponse::from_data(result))
 }
 
-/// Create a new subscription (without importing nodes)
+/// Create a new subscription (without importing nodes).
+///
+/// `url` is an `http(s)://` or `file://` URL; passing `content` instead creates a
+/// pasted-text subscription whose body is stored in the database.
 #[tauri::command(rename_all = "snake_case")]
 pub async fn create_subscription<'a>(
     db_state: State<'a, DatabaseState>,
     name: String,
     url: String,
+    options: Option<FetchOptions>,
+    filters: Option<NodeFilters>,
+    content: Option<String>,
 ) -> CommandResult<KittyResponse<SubscriptionInfo>> {
     let db = db_state.get_db();
 
     // Validate URL
-    if !url.starts_with("http://") && !url.starts_with("https://") {
-        return Err(anyhow!("Only HTTP/HTTPS subscription URLs are supported").into());
-    }
+    let kind = SubscriptionSource::new(&url, content.clone())?.kind();
 
     // Check if URL already exists
-    let existing = subscribe::Entity::find()
-        .filter(subscribe::Column::Url.eq(&url))
-        .one(&db)
-        .await?;
+    if kind != SubscriptionKind::Text {
+        let existing = subscribe::Entity::find()
+            .filter(subscribe::Column::Url.eq(&url))
+            .one(&db)
+            .await?;
 
-    if existing.is_some() {
-        return Err(anyhow!("Subscription URL already exists").into());
+        if existing.is_some() {
+            return Err(anyhow!("Subscription URL already exists").into());
+        }
     }
 
     // Create subscription record
     use sea_orm::ActiveModelTrait;
     use sea_orm::Set;
-    let now = Utc::now();
-    let active_model = subscribe::ActiveModel {
-        name: Set(name),
-        url: Set(url),
-        is_active: Set(false),
-        created_at: Set(now),
-        updated_at: Set(now),
-        last_sync_at: Set(None),
-        ..Default::default()
-    };
+    let mut active_model = new_subscription(name, url, kind, content);
+    options.unwrap_or_default().apply(&mut active_model);
+    active_model.filters = Set(filters);
 
     let record = active_model.insert(&db).await?;
 
-    Ok(KittyResponse::from_data(SubscriptionInfo {
-        id: record.id,
-        name: record.name,
-        url: record.url,
-        is_active: record.is_active,
-        node_count: 0,
-        created_at: record.created_at.to_rfc3339(),
-        updated_at: record.updated_at.to_rfc3339(),
-        last_sync_at: record.last_sync_at.map(|dt| dt.to_rfc3339()),
-    }))
+    Ok(KittyResponse::from_data(SubscriptionInfo::new(record, 0)))
 }
 
-/// Update subscription (name and URL)
+/// Update subscription (name, URL or pasted content and, if given, its HTTP options
+/// and filters)
 #[tauri::command(rename_all = "snake_case")]
 pub async fn update_subscription<'a>(
     db_state: State<'a, DatabaseState>,
     id: i32,
     name: String,
     url: String,
+    options: Option<FetchOptions>,
+    filters: Option<NodeFilters>,
+    content: Option<String>,
 ) -> CommandResult<KittyResponse<SubscriptionInfo>> {
     let db = db_state.get_db();
 
     // Validate URL
-    if !url.starts_with("http://") && !url.starts_with("https://") {
-        return Err(anyhow!("Only HTTP/HTTPS subscription URLs are supported").into());
-    }
+    let kind = SubscriptionSource::new(&url, content.clone())?.kind();
 
     // Check if URL already exists (excluding current subscription)
-    let existing = subscribe::Entity::find()
-        .filter(subscribe::Column::Url.eq(&url))
-        .filter(subscribe::Column::Id.ne(id))
-        .one(&db)
-        .await?;
+    if kind != SubscriptionKind::Text {
+        let existing = subscribe::Entity::find()
+            .filter(subscribe::Column::Url.eq(&url))
+            .filter(subscribe::Column::Id.ne(id))
+            .one(&db)
+            .await?;
 
-    if existing.is_some() {
-        return Err(anyhow!("Subscription URL already exists").into());
+        if existing.is_some() {
+            return Err(anyhow!("Subscription URL already exists").into());
+        }
     }
 
     // Get existing record
@@ -140,26 +201,36 @@ pub async fn update_subscription<'a>(
     // Update
     use sea_orm::ActiveModelTrait;
     use sea_orm::Set;
+    let record_filters = record.filters.clone();
+    let content_changed = kind == SubscriptionKind::Text && content != record.content;
+    let url_changed = url != record.url;
     let mut active_record: subscribe::ActiveModel = record.into();
     active_record.name = Set(name);
     active_record.url = Set(url);
+    active_record.kind = Set(kind);
+    active_record.content = Set(content);
     active_record.updated_at = Set(Utc::now());
+    if let Some(options) = options {
+        options.apply(&mut active_record);
+    }
+    let filters_changed = filters.as_ref().is_some_and(|x| Some(x) != record_filters.as_ref());
+    if let Some(filters) = filters {
+        active_record.filters = Set(Some(filters));
+    }
 
     let updated = active_record.update(&db).await?;
+    if url_changed {
+        // The validators belong to the old URL
+        subscription_snapshot::Model::clear_validators(&db, id).await?;
+    }
+    if content_changed {
+        SubscriptionAPI::refresh(&db, updated.clone()).await?;
+    } else if filters_changed {
+        SubscriptionAPI::reapply_filters(&db, updated.clone()).await?;
+    }
 
-    Ok(KittyResponse::from_data(SubscriptionInfo {
-        id: updated.id,
-        name: updated.name,
-        url: updated.url,
-        is_active: updated.is_active,
-        node_count: xray::Entity::find()
-            .filter(xray::Column::SubscribeId.eq(id))
-            .count(&db)
-            .await? as i64,
-        created_at: updated.created_at.to_rfc3339(),
-        updated_at: updated.updated_at.to_rfc3339(),
-        last_sync_at: updated.last_sync_at.map(|dt| dt.to_rfc3339()),
-    }))
+    let node_count = count_subscription_nodes(&db, id).await?;
+    Ok(KittyResponse::from_data(SubscriptionInfo::new(updated, node_count)))
 }
 
 /// Delete subscription and its associated nodes
@@ -178,11 +249,9 @@ pub async fn delete_subscription<'a>(
     // Use transaction for atomic delete
     let txn = db.begin().await?;
 
-    // Delete associated xray nodes
-    xray::Entity::delete_many()
-        .filter(xray::Column::SubscribeId.eq(id))
-        .exec(&txn)
-        .await?;
+    // Delete associated nodes and snapshots
+    SubscriptionNodes::delete_by_subscribe_id(&txn, id).await?;
+    subscription_snapshot::Model::delete_by_subscribe_id(&txn, id).await?;
 
     // Delete subscription
     subscribe::Model::delete_by_id(&txn, id).await?;
@@ -192,77 +261,61 @@ pub async fn delete_subscription<'a>(
     Ok(KittyResponse::default())
 }
 
-/// Switch to a subscription (clear old nodes, import new ones)
+/// Enable or disable a subscription. Several subscriptions can be enabled at once;
+/// disabling one hides its nodes without deleting them. Enabling a subscription that
+/// has no nodes yet downloads them.
 #[tauri::command(rename_all = "snake_case")]
-pub async fn switch_subscription<'a>(
+pub async fn set_subscription_enabled<'a>(
     db_state: State<'a, DatabaseState>,
     id: i32,
-) -> CommandResult<KittyResponse<()>> {
+    enabled: bool,
+) -> CommandResult<KittyResponse<SubscriptionInfo>> {
     let db = db_state.get_db();
 
-    // Get target subscription
-    let target_sub = subscribe::Model::get_by_id(&db, id)
+    let sub = subscribe::Model::get_by_id(&db, id)
         .await?
         .ok_or_else(|| anyhow!("Subscription not found"))?;
 
-    // Download and parse new subscription BEFORE transaction
-    let subscriptions = crate::apis::parse_subscription::download_subcriptions(&target_sub.url)
-        .await?;
-
-    // Parse xray records BEFORE transaction
-    let mut xray_models = Vec::new();
-    for line in subscriptions {
-        if !line.is_xray() {
-            continue;
-        }
-        if let Ok(mut xray_model) = xray::Model::from_str(&line.line.trim()) {
-            xray_model.subscribe_id = Some(id);
-            xray_models.push(xray_model);
-        }
-    }
-
-    if xray_models.is_empty() {
-        return Err(anyhow!("No valid xray proxies found in subscription").into());
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::Set;
+    let mut active_record: subscribe::ActiveModel = sub.into();
+    active_record.enabled = Set(enabled);
+    active_record.updated_at = Set(Utc::now());
+    let mut updated = active_record.update(&db).await?;
+
+    let mut node_count = count_subscription_nodes(&db, id).await?;
+    if enabled && node_count == 0 {
+        SubscriptionAPI::refresh(&db, updated).await?;
+        updated = subscribe::Model::get_by_id(&db, id)
+            .await?
+            .ok_or_else(|| anyhow!("Subscription not found"))?;
+        node_count = count_subscription_nodes(&db, id).await?;
     }
 
-    // NOW start transaction (after validation passes)
-    let txn = db.begin().await?;
-
-    // Find old active subscription
-    if let Ok(Some(old_active)) = subscribe::Entity::find()
-        .filter(subscribe::Column::IsActive.eq(true))
-        .one(&txn)
-        .await
-    {
-        // Delete old active subscription's nodes
-        xray::Entity::delete_many()
-            .filter(xray::Column::SubscribeId.eq(old_active.id))
-            .exec(&txn)
-            .await?;
+    Ok(KittyResponse::from_data(SubscriptionInfo::new(updated, node_count)))
+}
 
-        // Deactivate old subscription
-        use sea_orm::ActiveModelTrait;
-        use sea_orm::Set;
-        let mut old_active_model: subscribe::ActiveModel = old_active.into();
-        old_active_model.is_active = Set(false);
-        old_active_model.update(&txn).await?;
-    }
+/// Legacy alias: enable a subscription and refresh it. Other enabled subscriptions
+/// keep their nodes.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn switch_subscription<'a>(
+    db_state: State<'a, DatabaseState>,
+    id: i32,
+) -> CommandResult<KittyResponse<ImportReport>> {
+    let db = db_state.get_db();
 
-    // Insert new nodes
-    xray::Model::insert_many(&txn, xray_models).await?;
+    let sub = subscribe::Model::get_by_id(&db, id)
+        .await?
+        .ok_or_else(|| anyhow!("Subscription not found"))?;
 
-    // Set new subscription as active and update timestamp
     use sea_orm::ActiveModelTrait;
     use sea_orm::Set;
-    let mut target_model: subscribe::ActiveModel = target_sub.into();
-    target_model.is_active = Set(true);
-    target_model.updated_at = Set(Utc::now());
-    target_model.last_sync_at = Set(Some(Utc::now()));
-    target_model.update(&txn).await?;
-
-    txn.commit().await?;
+    let mut active_record: subscribe::ActiveModel = sub.into();
+    active_record.enabled = Set(true);
+    let updated = active_record.update(&db).await?;
 
-    Ok(KittyResponse::default())
+    let report = SubscriptionAPI::refresh(&db, updated).await?;
+    Ok(KittyResponse::from_data(report))
 }
 
 /// Refresh (re-import nodes) for a subscription by ID
@@ -270,7 +323,7 @@ pub async fn switch_subscription<'a>(
 pub async fn refresh_subscription<'a>(
     db_state: State<'a, DatabaseState>,
     id: i32,
-) -> CommandResult<KittyResponse<()>> {
+) -> CommandResult<KittyResponse<ImportReport>> {
     let db = db_state.get_db();
 
     // Get subscription
@@ -278,45 +331,92 @@ pub async fn refresh_subscription<'a>(
         .await?
         .ok_or_else(|| anyhow!("Subscription not found"))?;
 
-    // Download and parse BEFORE transaction
-    let subscriptions = crate::apis::parse_subscription::download_subcriptions(&sub.url)
-        .await?;
+    let report = SubscriptionAPI::refresh(&db, sub).await?;
+    Ok(KittyResponse::from_data(report))
+}
 
-    // Parse xray records BEFORE transaction
-    let mut xray_models = Vec::new();
-    for line in subscriptions {
-        if !line.is_xray() {
-            continue;
-        }
-        if let Ok(mut xray_model) = xray::Model::from_str(&line.line.trim()) {
-            xray_model.subscribe_id = Some(id);
-            xray_models.push(xray_model);
-        }
-    }
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SnapshotInfo {
+    pub id: i32,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub created_at: String,
+    /// Size of the raw body in bytes
+    pub size: usize,
+}
 
-    // NOW start transaction
-    let txn = db.begin().await?;
+/// List the stored raw snapshots of a subscription, newest first
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_subscription_snapshots<'a>(
+    db_state: State<'a, DatabaseState>,
+    id: i32,
+) -> CommandResult<KittyResponse<Vec<SnapshotInfo>>> {
+    let db = db_state.get_db();
+    let snapshots = subscription_snapshot::Model::fetch_by_subscribe_id(&db, id)
+        .await?
+        .into_iter()
+        .map(|x| SnapshotInfo {
+            id: x.id,
+            etag: x.etag,
+            last_modified: x.last_modified,
+            created_at: x.created_at.to_rfc3339(),
+            size: x.content.len(),
+        })
+        .collect();
+    Ok(KittyResponse::from_data(snapshots))
+}
 
-    // Delete old nodes
-    xray::Entity::delete_many()
-        .filter(xray::Column::SubscribeId.eq(id))
-        .exec(&txn)
-        .await?;
+/// Restore a subscription's nodes from an earlier snapshot
+#[tauri::command(rename_all = "snake_case")]
+pub async fn rollback_subscription<'a>(
+    db_state: State<'a, DatabaseState>,
+    id: i32,
+    snapshot_id: i32,
+) -> CommandResult<KittyResponse<ImportReport>> {
+    let db = db_state.get_db();
 
-    // Insert new nodes (if any)
-    if !xray_models.is_empty() {
-        xray::Model::insert_many(&txn, xray_models).await?;
-    }
+    let sub = subscribe::Model::get_by_id(&db, id)
+        .await?
+        .ok_or_else(|| anyhow!("Subscription not found"))?;
 
-    // Update subscription timestamp
-    use sea_orm::ActiveModelTrait;
-    use sea_orm::Set;
-    let mut sub_model: subscribe::ActiveModel = sub.into();
-    sub_model.updated_at = Set(Utc::now());
-    sub_model.last_sync_at = Set(Some(Utc::now()));
-    sub_model.update(&txn).await?;
+    let report = SubscriptionAPI::rollback(&db, sub, snapshot_id).await?;
+    Ok(KittyResponse::from_data(report))
+}
 
-    txn.commit().await?;
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SubscriptionPreview {
+    /// Nodes parsed before filtering
+    pub total: usize,
+    /// Nodes that would be saved
+    pub nodes: Vec<Proxy>,
+    pub report: ImportReport,
+}
 
-    Ok(KittyResponse::default())
+/// Download a subscription and show which nodes the given filters keep, without saving anything
+#[tauri::command(rename_all = "snake_case")]
+pub async fn preview_subscription<'a>(
+    db_state: State<'a, DatabaseState>,
+    url: String,
+    options: Option<FetchOptions>,
+    filters: Option<NodeFilters>,
+    content: Option<String>,
+) -> CommandResult<KittyResponse<SubscriptionPreview>> {
+    let source = SubscriptionSource::new(&url, content)?;
+    let options = {
+        let db = db_state.get_db();
+        options.unwrap_or_default().with_local_proxy(&db).await?
+    };
+    let download = source.load(&options).await?;
+    let mut nodes = parse_subscription_text(&download.text);
+    let total = nodes.xray.len() + nodes.hysteria.len();
+    nodes.apply_filters(&filters.unwrap_or_default())?;
+    let report = nodes.take_report();
+
+    let mut proxies: Vec<Proxy> = nodes.xray.into_iter().map(Proxy::from).collect();
+    proxies.extend(nodes.hysteria.into_iter().map(Proxy::from));
+    Ok(KittyResponse::from_data(SubscriptionPreview {
+        total,
+        nodes: proxies,
+        report,
+    }))
 }
@@ -239,6 +239,28 @@ fn setup_auto_start_fastest<'a>(handle: &tauri::AppHandle) -> Result<(), Box<dyn
     Ok(())
 }
 
+/// Start the local share server if it was enabled in the base config.
+fn setup_share_server(handle: &tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let db_state: State<DatabaseState> = handle.state();
+        let share_server: State<crate::share_server::ShareServerState> = handle.state();
+        if let Err(e) = share_server.restart(&db_state.get_db()).await {
+            log::error!("Failed to start share server: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Start refreshing subscriptions in the background, independent of the main window.
+fn setup_subscription_scheduler(handle: &tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::subscription_scheduler::SubscriptionScheduler;
+
+    let db_state: State<DatabaseState> = handle.state();
+    SubscriptionScheduler::new(handle.clone(), db_state.get_db()).spawn();
+    Ok(())
+}
+
 pub fn init_setup<'a>(app: &'a mut tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     let handle = app.handle();
     let _ = setup_kitty_logger(handle)?;
@@ -246,6 +268,8 @@ pub fn init_setup<'a>(app: &'a mut tauri::App) -> Result<(), Box<dyn std::error:
     let _ = setup_log_level_from_db(handle)?;
     let _ = setup_system_autostart(handle)?;
     let _ = setup_auto_start_fastest(handle)?;
+    let _ = setup_subscription_scheduler(handle)?;
+    let _ = setup_share_server(handle)?;
     let _ = Tray::init_tray(handle)?;
     Ok(())
 }
@@ -27,8 +27,9 @@ export async function deleteSubscription(id: number): Promise<void> {
   await invoke('delete_subscription', { id })
 }
 
-export async function switchSubscription(id: number): Promise<void> {
-  await invoke('switch_subscription', { id })
+export async function setSubscriptionEnabled(id: number, enabled: boolean): Promise<SubscriptionInfo> {
+  const res = await invoke<SubscriptionInfo>('set_subscription_enabled', { id, enabled })
+  return res.data
 }
 
 export async function refreshSubscription(id: number): Promise<void> {
@@ -82,6 +82,9 @@ export class Xray {
   @Expose({ name: 'subscribe_id' })
   subscribeId?: number
 
+  @Expose()
+  flow?: string
+
   @Type(() => StreamSettings)
   @Expose({ name: 'stream_settings', toPlainOnly: true })
   streamSettings!: StreamSettings
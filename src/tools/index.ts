@@ -1,4 +1,3 @@
-import { useSubscriptionAutoUpdate } from '@/tools/autoUpdateHook'
 import { useQueueRef } from '@/tools/logHook'
 
-export { useSubscriptionAutoUpdate, useQueueRef }
+export { useQueueRef }
@@ -11,4 +11,5 @@ export interface KittyBaseConfig {
   mode: 'Global' | 'Rules' | 'Direct'
   updateInterval: number
   logLevel: 'debug' | 'info' | 'warn' | 'error'
+  skipDuplicateNodes: boolean
 }
@@ -2,7 +2,7 @@ export interface SubscriptionInfo {
   id: number
   name: string
   url: string
-  isActive: boolean
+  enabled: boolean
   nodeCount: number
   createdAt: string
   updatedAt: string
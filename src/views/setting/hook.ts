@@ -21,6 +21,7 @@ export function useConfig() {
     mode: 'Rules',
     updateInterval: 3,
     logLevel: 'debug',
+    skipDuplicateNodes: false,
   })
 
   async function getBaseConfig() {
@@ -77,6 +78,10 @@ export function useConfig() {
     await handleBaseConfigUpdate()
   }
 
+  async function handleSkipDuplicateNodesChange(value: boolean) {
+    await invoke('set_skip_duplicate_nodes', { skip_duplicate_nodes: value })
+  }
+
   return {
     loading,
     proxyLoading,
@@ -85,6 +90,7 @@ export function useConfig() {
     handleSwitchAutoStart,
     handleBaseConfigUpdate,
     handleLogLevelChange,
+    handleSkipDuplicateNodesChange,
     initConfig,
   }
 }